@@ -0,0 +1,418 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use super::{Block, ClickEvent, Header, ParseError, ParseMode};
+use framing::{ArrayFraming, Frame, LineBuffer, StatusLineFraming};
+use writer;
+
+const READ_SIZE: usize = 4096;
+
+fn poll_line<R>(
+    reader: &mut R,
+    lines: &mut LineBuffer,
+    cx: &mut Context,
+) -> Poll<Result<Option<String>, ParseError>>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        if let Some(line) = lines.next_line() {
+            return Poll::Ready(line.map(Some));
+        }
+
+        let mut chunk = [0; READ_SIZE];
+        let mut buf = ReadBuf::new(&mut chunk);
+        match Pin::new(&mut *reader).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+                return Poll::Ready(Ok(lines.take_remaining()));
+            }
+            Poll::Ready(Ok(())) => lines.extend(buf.filled()),
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+}
+
+pub struct StatusLineStream<R> {
+    reader: R,
+    header: Option<Header>,
+    lines: LineBuffer,
+    framing: StatusLineFraming,
+    frames: VecDeque<Result<Frame, ParseError>>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> StatusLineStream<R> {
+    pub fn new(reader: R) -> StatusLineStream<R> {
+        StatusLineStream::with_mode(reader, ParseMode::Normal)
+    }
+
+    pub fn with_mode(reader: R, mode: ParseMode) -> StatusLineStream<R> {
+        StatusLineStream {
+            reader,
+            header: None,
+            lines: LineBuffer::new(),
+            framing: StatusLineFraming::with_mode(mode),
+            frames: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    // Available once the first line of the stream has been read.
+    pub fn header(&self) -> Option<&Header> {
+        self.header.as_ref()
+    }
+
+    pub fn is_plain_text(&self) -> bool {
+        self.framing.is_plain_text()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for StatusLineStream<R> {
+    type Item = Result<Vec<Block>, ParseError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            match this.frames.pop_front() {
+                Some(Ok(Frame::Header(header))) => {
+                    this.header = Some(header);
+                    continue;
+                }
+                Some(Ok(Frame::StatusLine(blocks))) => return Poll::Ready(Some(Ok(blocks))),
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => {}
+            }
+
+            if this.done || this.framing.is_closed() {
+                return Poll::Ready(None);
+            }
+
+            match poll_line(&mut this.reader, &mut this.lines, cx) {
+                Poll::Ready(Ok(Some(line))) => this.framing.push_line(&line, &mut this.frames),
+                Poll::Ready(Ok(None)) => {
+                    this.done = true;
+                    this.frames.extend(this.framing.finish().map(Err));
+                    if this.header.is_none() {
+                        return Poll::Ready(Some(Err(ParseError::ReadError(String::from(
+                            "Stream ended before header",
+                        )))));
+                    }
+                }
+                Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+pub struct ClickEventStream<R> {
+    reader: R,
+    lines: LineBuffer,
+    framing: ArrayFraming,
+    events: VecDeque<Result<ClickEvent, ParseError>>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> ClickEventStream<R> {
+    pub fn new(reader: R) -> ClickEventStream<R> {
+        ClickEventStream::with_mode(reader, ParseMode::Normal)
+    }
+
+    pub fn with_mode(reader: R, mode: ParseMode) -> ClickEventStream<R> {
+        ClickEventStream {
+            reader,
+            lines: LineBuffer::new(),
+            framing: ArrayFraming::click_events(mode),
+            events: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for ClickEventStream<R> {
+    type Item = Result<ClickEvent, ParseError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(event) = this.events.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            if this.done || this.framing.is_closed() {
+                return Poll::Ready(None);
+            }
+
+            match poll_line(&mut this.reader, &mut this.lines, cx) {
+                Poll::Ready(Ok(Some(line))) => {
+                    let events = &mut this.events;
+                    this.framing
+                        .elements(&line, |event| events.push_back(event));
+                }
+                Poll::Ready(Ok(None)) => {
+                    this.done = true;
+                    this.events.extend(this.framing.finish().map(Err));
+                }
+                Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+// Buffers encoded output and writes it out on flush, like the sync writers do per item.
+struct WriteBuffer<W> {
+    writer: W,
+    buffer: Vec<u8>,
+    written: usize,
+}
+
+impl<W: AsyncWrite + Unpin> WriteBuffer<W> {
+    fn new(writer: W) -> WriteBuffer<W> {
+        WriteBuffer {
+            writer,
+            buffer: Vec::new(),
+            written: 0,
+        }
+    }
+
+    fn poll_flush(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        while self.written < self.buffer.len() {
+            let count =
+                match Pin::new(&mut self.writer).poll_write(cx, &self.buffer[self.written..]) {
+                    Poll::Ready(Ok(count)) => count,
+                    Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                    Poll::Pending => return Poll::Pending,
+                };
+            if count == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::WriteZero,
+                    "failed to write buffered data",
+                )));
+            }
+            self.written += count;
+        }
+
+        self.buffer.clear();
+        self.written = 0;
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        if self.buffer.len() >= READ_SIZE {
+            self.poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+pub struct StatusLineSink<W> {
+    inner: WriteBuffer<W>,
+    first_line: bool,
+    closed: bool,
+}
+
+impl<W: AsyncWrite + Unpin> StatusLineSink<W> {
+    // The header and opening bracket are sent with the first flush.
+    pub fn new(writer: W, header: &Header) -> StatusLineSink<W> {
+        let mut inner = WriteBuffer::new(writer);
+        writer::write_status_preamble(&mut inner.buffer, header)
+            .expect("Writing to a Vec cannot fail");
+
+        StatusLineSink {
+            inner,
+            first_line: true,
+            closed: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<Vec<Block>> for StatusLineSink<W> {
+    type Error = io::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, blocks: Vec<Block>) -> io::Result<()> {
+        let this = &mut *self;
+        writer::write_status_line(&mut this.inner.buffer, &blocks, this.first_line)?;
+        this.first_line = false;
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        if !this.closed {
+            writer::write_closing(&mut this.inner.buffer)?;
+            this.closed = true;
+        }
+
+        match this.inner.poll_flush(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner.writer).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+pub struct ClickEventSink<W> {
+    inner: WriteBuffer<W>,
+    first_event: bool,
+}
+
+impl<W: AsyncWrite + Unpin> ClickEventSink<W> {
+    // The opening bracket is sent with the first flush.
+    pub fn new(writer: W) -> ClickEventSink<W> {
+        let mut inner = WriteBuffer::new(writer);
+        writer::write_click_preamble(&mut inner.buffer).expect("Writing to a Vec cannot fail");
+
+        ClickEventSink {
+            inner,
+            first_event: true,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner.writer
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<ClickEvent> for ClickEventSink<W> {
+    type Error = io::Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, event: ClickEvent) -> io::Result<()> {
+        let this = &mut *self;
+        writer::write_click_event(&mut this.inner.buffer, &event, this.first_event)?;
+        this.first_event = false;
+        Ok(())
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        self.inner.poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        match this.inner.poll_flush(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner.writer).poll_shutdown(cx),
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::{SinkExt, StreamExt};
+    use super::super::{
+        BlockBuilder, ClickEventBuilder, ClickEventReader, HeaderBuilder, MouseButton,
+        StatusLineReader,
+    };
+
+    #[test]
+    fn it_streams_status_lines() {
+        let input: &[u8] =
+            b"{\"version\":1,\"click_events\":true}\n[\n[{\"full_text\":\"a\"}]\ngarbage\n,[{\"full_text\":\"b\"}]";
+        let mut stream = StatusLineStream::new(input);
+        assert!(stream.header().is_none());
+
+        let line = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(line[0].full_text(), "a");
+        assert!(stream.header().unwrap().click_events());
+
+        let error = block_on(stream.next()).unwrap().unwrap_err();
+        assert_eq!(error.raw_line(), Some("garbage"));
+
+        let line = block_on(stream.next()).unwrap().unwrap();
+        assert_eq!(line[0].full_text(), "b");
+        assert!(block_on(stream.next()).is_none());
+    }
+
+    #[test]
+    fn it_streams_plain_text() {
+        let input: &[u8] = b"hello\nworld\n";
+        let lines: Vec<_> = block_on(StatusLineStream::new(input).collect::<Vec<_>>());
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1].as_ref().unwrap()[0].full_text(), "world");
+    }
+
+    #[test]
+    fn it_streams_click_events() {
+        let input: &[u8] = b"[\n{\"name\":\"a\",\"button\":1}\n,{\"name\":\"b\",\"button\":3}\n";
+        let events: Vec<_> = block_on(ClickEventStream::new(input).collect::<Vec<_>>());
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap().name(), "a");
+        assert_eq!(events[1].as_ref().unwrap().button(), MouseButton::Right);
+    }
+
+    #[test]
+    fn it_sinks_status_lines_like_the_sync_writer() {
+        let header = HeaderBuilder::new().build();
+        let mut sink = StatusLineSink::new(Vec::new(), &header);
+
+        block_on(sink.send(vec![BlockBuilder::new("a").build()])).unwrap();
+        block_on(sink.send(vec![BlockBuilder::new("b").build()])).unwrap();
+        block_on(sink.close()).unwrap();
+        let output = sink.into_inner();
+
+        let mut writer = writer::StatusLineWriter::new(Vec::new(), &header).unwrap();
+        writer
+            .write_line(&[BlockBuilder::new("a").build()])
+            .unwrap();
+        writer
+            .write_line(&[BlockBuilder::new("b").build()])
+            .unwrap();
+        assert_eq!(output, writer.close().unwrap());
+
+        let lines: Vec<Vec<Block>> = StatusLineReader::new(&output[..])
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn it_sinks_click_events() {
+        let mut sink = ClickEventSink::new(Vec::new());
+        block_on(sink.send(ClickEventBuilder::new("a", MouseButton::Left).build())).unwrap();
+        block_on(sink.send(ClickEventBuilder::new("b", MouseButton::Middle).build())).unwrap();
+        let output = sink.into_inner();
+
+        let events: Vec<ClickEvent> = ClickEventReader::new(&output[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].button(), MouseButton::Middle);
+    }
+}
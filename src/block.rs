@@ -1,35 +1,86 @@
 extern crate serde_json;
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt;
 use std::str::FromStr;
+use serde::de::DeserializeOwned;
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
-use super::{ParseError, Markup, Alignment};
+use serde_json::Value;
+use super::{
+    Alignment, Color, Markup, MarkupError, PangoMarkup, ParseError, ParseMode, TextRun, Warning,
+};
+use mode;
+use pango_parser;
+use validation;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+// Serialization leaves out unset and default values. `separator` is always written, as i3bar
+// defaults it to true while blocks here default to false.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Block {
+    #[serde(skip_serializing_if = "Option::is_none")]
     name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     instance: Option<String>,
 
     full_text: String,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     urgent: bool,
 
     #[serde(default)]
     separator: bool,
 
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default")]
     markup: Markup,
 
-    #[serde(default, rename = "align")]
+    #[serde(default, rename = "align", skip_serializing_if = "is_default")]
     alignment: Alignment,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
     short_text: Option<String>,
-    color: Option<String>,
-    background: Option<String>,
-    border: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    background: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    border: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     min_width: Option<MinWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     separator_block_width: Option<u32>,
+
+    // swaybar extensions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    border_top: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    border_bottom: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    border_left: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    border_right: Option<u32>,
+
+    #[serde(flatten, deserialize_with = "deserialize_extras")]
+    extras: BTreeMap<String, Value>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+// i3bar ignores keys starting with an underscore, so status programs may use them to tag blocks.
+// Any other unknown keys are dropped, as before.
+fn is_extra_key(key: &str) -> bool {
+    key.starts_with('_')
+}
+
+fn deserialize_extras<'de, D>(deserializer: D) -> Result<BTreeMap<String, Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut extras = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    extras.retain(|key, _| is_extra_key(key));
+    Ok(extras)
 }
 
 impl FromStr for Block {
@@ -40,7 +91,18 @@ impl FromStr for Block {
     }
 }
 
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let string = serde_json::to_string(self).unwrap_or_else(|_| String::from("{}"));
+        f.write_str(&string)
+    }
+}
+
 impl Block {
+    pub fn from_str_mode(str: &str, mode: ParseMode) -> Result<Block, ParseError> {
+        mode::parse(str, &mode::BLOCK, mode)
+    }
+
     pub fn full_text(&self) -> &str {
         &self.full_text
     }
@@ -69,16 +131,704 @@ impl Block {
         self.markup
     }
 
-    pub fn color(&self) -> Option<&str> {
-        self.color.as_ref().map(String::as_ref)
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    pub fn border(&self) -> Option<Color> {
+        self.border
+    }
+
+    pub fn background(&self) -> Option<Color> {
+        self.background
+    }
+
+    // The colors as the hex strings they are written out as, for code that works with strings.
+    pub fn color_str(&self) -> Option<String> {
+        self.color.map(|color| color.to_hex())
+    }
+
+    pub fn border_str(&self) -> Option<String> {
+        self.border.map(|color| color.to_hex())
+    }
+
+    pub fn background_str(&self) -> Option<String> {
+        self.background.map(|color| color.to_hex())
+    }
+
+    pub fn is_urgent(&self) -> bool {
+        self.urgent
+    }
+
+    pub fn has_separator(&self) -> bool {
+        self.separator
+    }
+
+    pub fn separator_block_width(&self) -> Option<u32> {
+        self.separator_block_width
+    }
+
+    pub fn border_top(&self) -> Option<u32> {
+        self.border_top
+    }
+
+    pub fn border_bottom(&self) -> Option<u32> {
+        self.border_bottom
+    }
+
+    pub fn border_left(&self) -> Option<u32> {
+        self.border_left
+    }
+
+    pub fn border_right(&self) -> Option<u32> {
+        self.border_right
+    }
+
+    // swaybar draws each side of the border 1px wide unless told otherwise, but only when a
+    // border color is set.
+    pub fn border_widths(&self) -> BorderWidths {
+        if self.border.is_none() {
+            return BorderWidths::default();
+        }
+
+        BorderWidths {
+            top: self.border_top.unwrap_or(1),
+            bottom: self.border_bottom.unwrap_or(1),
+            left: self.border_left.unwrap_or(1),
+            right: self.border_right.unwrap_or(1),
+        }
+    }
+
+    // Text without Pango markup is a single run without any attributes.
+    pub fn full_text_runs(&self) -> Result<Vec<TextRun>, MarkupError> {
+        self.text_runs(&self.full_text)
+    }
+
+    pub fn short_text_runs(&self) -> Option<Result<Vec<TextRun>, MarkupError>> {
+        self.short_text
+            .as_ref()
+            .map(|short_text| self.text_runs(short_text))
+    }
+
+    fn text_runs(&self, text: &str) -> Result<Vec<TextRun>, MarkupError> {
+        match self.markup {
+            Markup::Pango => pango_parser::parse_markup(text),
+            Markup::None if text.is_empty() => Ok(Vec::new()),
+            Markup::None => Ok(vec![TextRun {
+                text: text.to_owned(),
+                attributes: Default::default(),
+            }]),
+        }
+    }
+
+    // The visible text, with tags stripped and entities decoded when the block uses Pango markup.
+    pub fn plain_full_text(&self) -> Result<Cow<'_, str>, MarkupError> {
+        self.plain_text(&self.full_text)
+    }
+
+    pub fn plain_short_text(&self) -> Option<Result<Cow<'_, str>, MarkupError>> {
+        self.short_text
+            .as_ref()
+            .map(|short_text| self.plain_text(short_text))
+    }
+
+    fn plain_text<'a>(&self, text: &'a str) -> Result<Cow<'a, str>, MarkupError> {
+        match self.markup {
+            Markup::Pango => {
+                let runs = pango_parser::parse_markup(text)?;
+                Ok(Cow::Owned(runs.into_iter().map(|run| run.text).collect()))
+            }
+            Markup::None => Ok(Cow::Borrowed(text)),
+        }
+    }
+
+    pub fn validate(&self) -> Vec<Warning> {
+        validation::validate_block(0, self)
+    }
+
+    pub fn extras(&self) -> &BTreeMap<String, Value> {
+        &self.extras
+    }
+
+    pub fn extra(&self, key: &str) -> Option<&Value> {
+        self.extras.get(key)
+    }
+
+    pub fn get_extra<T>(&self, key: &str) -> Result<Option<T>, ParseError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.extras.get(key) {
+            Some(value) => T::deserialize(value).map(Some).map_err(Into::into),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_extra<K, T>(&mut self, key: K, value: T) -> Result<bool, ParseError>
+    where
+        K: Into<String>,
+        T: Serialize,
+    {
+        let key = key.into();
+        if !is_extra_key(&key) {
+            return Err(ParseError::InvalidData(format!(
+                "Custom block keys must start with an underscore, got {:?}",
+                key
+            )));
+        }
+
+        let value = serde_json::to_value(value)?;
+        if self.extras.get(&key) == Some(&value) {
+            Ok(false)
+        } else {
+            self.extras.insert(key, value);
+            Ok(true)
+        }
+    }
+
+    pub fn remove_extra(&mut self, key: &str) -> Option<Value> {
+        self.extras.remove(key)
+    }
+
+    // Setters return whether the block was changed, so callers can skip re-emitting a status
+    // line when nothing differs.
+    pub fn set_full_text<S>(&mut self, value: S) -> bool
+    where
+        S: Into<String>,
+    {
+        replace(&mut self.full_text, value.into())
+    }
+
+    // Also switches the block to Pango markup.
+    pub fn set_pango_markup(&mut self, value: PangoMarkup) -> bool {
+        let text_changed = replace(&mut self.full_text, value.into_string());
+        let markup_changed = replace(&mut self.markup, Markup::Pango);
+        text_changed || markup_changed
+    }
+
+    pub fn set_short_text<V, S>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        replace(&mut self.short_text, value.into().map(Into::into))
+    }
+
+    pub fn set_name<V, S>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        replace(&mut self.name, value.into().map(Into::into))
+    }
+
+    pub fn set_instance<V, S>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        replace(&mut self.instance, value.into().map(Into::into))
+    }
+
+    pub fn set_color<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<Color>>,
+    {
+        replace(&mut self.color, value.into())
+    }
+
+    pub fn set_background<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<Color>>,
+    {
+        replace(&mut self.background, value.into())
+    }
+
+    pub fn set_border<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<Color>>,
+    {
+        replace(&mut self.border, value.into())
+    }
+
+    pub fn set_min_width<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<MinWidth>>,
+    {
+        replace(&mut self.min_width, value.into())
+    }
+
+    pub fn set_alignment(&mut self, value: Alignment) -> bool {
+        replace(&mut self.alignment, value)
+    }
+
+    pub fn set_markup(&mut self, value: Markup) -> bool {
+        replace(&mut self.markup, value)
+    }
+
+    pub fn set_urgent(&mut self, value: bool) -> bool {
+        replace(&mut self.urgent, value)
+    }
+
+    pub fn set_separator(&mut self, value: bool) -> bool {
+        replace(&mut self.separator, value)
+    }
+
+    pub fn set_separator_block_width<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<u32>>,
+    {
+        replace(&mut self.separator_block_width, value.into())
+    }
+
+    pub fn set_border_top<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<u32>>,
+    {
+        replace(&mut self.border_top, value.into())
+    }
+
+    pub fn set_border_bottom<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<u32>>,
+    {
+        replace(&mut self.border_bottom, value.into())
+    }
+
+    pub fn set_border_left<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<u32>>,
+    {
+        replace(&mut self.border_left, value.into())
+    }
+
+    pub fn set_border_right<V>(&mut self, value: V) -> bool
+    where
+        V: Into<Option<u32>>,
+    {
+        replace(&mut self.border_right, value.into())
+    }
+
+    pub fn with_full_text<S>(mut self, value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.set_full_text(value);
+        self
+    }
+
+    pub fn with_pango_markup(mut self, value: PangoMarkup) -> Self {
+        self.set_pango_markup(value);
+        self
+    }
+
+    pub fn with_short_text<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.set_short_text(value);
+        self
+    }
+
+    pub fn with_name<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.set_name(value);
+        self
+    }
+
+    pub fn with_instance<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.set_instance(value);
+        self
+    }
+
+    pub fn with_color<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.set_color(value);
+        self
+    }
+
+    pub fn with_background<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.set_background(value);
+        self
+    }
+
+    pub fn with_border<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.set_border(value);
+        self
+    }
+
+    pub fn with_min_width<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<MinWidth>>,
+    {
+        self.set_min_width(value);
+        self
+    }
+
+    pub fn with_alignment(mut self, value: Alignment) -> Self {
+        self.set_alignment(value);
+        self
+    }
+
+    pub fn with_markup(mut self, value: Markup) -> Self {
+        self.set_markup(value);
+        self
+    }
+
+    pub fn with_urgent(mut self, value: bool) -> Self {
+        self.set_urgent(value);
+        self
+    }
+
+    pub fn with_separator(mut self, value: bool) -> Self {
+        self.set_separator(value);
+        self
+    }
+
+    pub fn with_separator_block_width<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<u32>>,
+    {
+        self.set_separator_block_width(value);
+        self
+    }
+
+    pub fn with_border_top<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<u32>>,
+    {
+        self.set_border_top(value);
+        self
+    }
+
+    pub fn with_border_bottom<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<u32>>,
+    {
+        self.set_border_bottom(value);
+        self
+    }
+
+    pub fn with_border_left<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<u32>>,
+    {
+        self.set_border_left(value);
+        self
+    }
+
+    pub fn with_border_right<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<u32>>,
+    {
+        self.set_border_right(value);
+        self
+    }
+}
+
+fn replace<T: PartialEq>(slot: &mut T, value: T) -> bool {
+    if *slot == value {
+        false
+    } else {
+        *slot = value;
+        true
+    }
+}
+
+pub struct BlockBuilder {
+    full_text: String,
+
+    name: Option<String>,
+    instance: Option<String>,
+    short_text: Option<String>,
+    color: Option<Color>,
+    background: Option<Color>,
+    border: Option<Color>,
+    min_width: Option<MinWidth>,
+    alignment: Alignment,
+    markup: Markup,
+    urgent: bool,
+    separator: bool,
+    separator_block_width: Option<u32>,
+    border_top: Option<u32>,
+    border_bottom: Option<u32>,
+    border_left: Option<u32>,
+    border_right: Option<u32>,
+}
+
+impl BlockBuilder {
+    pub fn new<S>(full_text: S) -> BlockBuilder
+    where
+        S: Into<String>,
+    {
+        BlockBuilder {
+            full_text: full_text.into(),
+            name: None,
+            instance: None,
+            short_text: None,
+            color: None,
+            background: None,
+            border: None,
+            min_width: None,
+            alignment: Alignment::default(),
+            markup: Markup::default(),
+            urgent: false,
+            separator: true,
+            separator_block_width: None,
+            border_top: None,
+            border_bottom: None,
+            border_left: None,
+            border_right: None,
+        }
+    }
+
+    pub fn name<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.name = value.into().map(Into::into);
+        self
+    }
+
+    pub fn instance<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.instance = value.into().map(Into::into);
+        self
+    }
+
+    pub fn short_text<V, S>(mut self, value: V) -> Self
+    where
+        V: Into<Option<S>>,
+        S: Into<String>,
+    {
+        self.short_text = value.into().map(Into::into);
+        self
+    }
+
+    pub fn color<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.color = value.into();
+        self
+    }
+
+    pub fn background<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.background = value.into();
+        self
+    }
+
+    pub fn border<V>(mut self, value: V) -> Self
+    where
+        V: Into<Option<Color>>,
+    {
+        self.border = value.into();
+        self
+    }
+
+    pub fn min_width<M>(mut self, value: M) -> Self
+    where
+        M: Into<MinWidth>,
+    {
+        self.min_width = Some(value.into());
+        self
+    }
+
+    pub fn alignment(mut self, alignment: Alignment) -> Self {
+        self.alignment = alignment;
+        self
+    }
+
+    pub fn markup(mut self, markup: Markup) -> Self {
+        self.markup = markup;
+        self
+    }
+
+    // Replaces the full text and switches the block to Pango markup.
+    pub fn pango_markup(mut self, markup: PangoMarkup) -> Self {
+        self.full_text = markup.into_string();
+        self.markup = Markup::Pango;
+        self
+    }
+
+    pub fn urgent(mut self, urgent: bool) -> Self {
+        self.urgent = urgent;
+        self
+    }
+
+    pub fn separator(mut self, separator: bool) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    pub fn separator_block_width(mut self, width: u32) -> Self {
+        self.separator_block_width = Some(width);
+        self
+    }
+
+    pub fn border_top(mut self, width: u32) -> Self {
+        self.border_top = Some(width);
+        self
+    }
+
+    pub fn border_bottom(mut self, width: u32) -> Self {
+        self.border_bottom = Some(width);
+        self
+    }
+
+    pub fn border_left(mut self, width: u32) -> Self {
+        self.border_left = Some(width);
+        self
+    }
+
+    pub fn border_right(mut self, width: u32) -> Self {
+        self.border_right = Some(width);
+        self
+    }
+
+    pub fn build(self) -> Block {
+        Block {
+            name: self.name,
+            instance: self.instance,
+            full_text: self.full_text,
+            urgent: self.urgent,
+            separator: self.separator,
+            markup: self.markup,
+            alignment: self.alignment,
+            short_text: self.short_text,
+            color: self.color,
+            background: self.background,
+            border: self.border,
+            min_width: self.min_width,
+            separator_block_width: self.separator_block_width,
+            border_top: self.border_top,
+            border_bottom: self.border_bottom,
+            border_left: self.border_left,
+            border_right: self.border_right,
+            extras: BTreeMap::new(),
+        }
+    }
+}
+
+// A block that borrows its text fields from the JSON input whenever they contain no escape
+// sequences, avoiding an allocation per string for every block of every status line.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BlockRef<'a> {
+    #[serde(borrow, default, deserialize_with = "borrow_optional")]
+    name: Option<Cow<'a, str>>,
+    #[serde(borrow, default, deserialize_with = "borrow_optional")]
+    instance: Option<Cow<'a, str>>,
+
+    #[serde(borrow)]
+    full_text: Cow<'a, str>,
+
+    #[serde(default)]
+    urgent: bool,
+
+    #[serde(default)]
+    separator: bool,
+
+    #[serde(default)]
+    markup: Markup,
+
+    #[serde(default, rename = "align")]
+    alignment: Alignment,
+
+    #[serde(borrow, default, deserialize_with = "borrow_optional")]
+    short_text: Option<Cow<'a, str>>,
+    color: Option<Color>,
+    background: Option<Color>,
+    border: Option<Color>,
+    min_width: Option<MinWidth>,
+    separator_block_width: Option<u32>,
+
+    border_top: Option<u32>,
+    border_bottom: Option<u32>,
+    border_left: Option<u32>,
+    border_right: Option<u32>,
+
+    #[serde(flatten, deserialize_with = "deserialize_extras")]
+    extras: BTreeMap<String, Value>,
+}
+
+impl<'a> BlockRef<'a> {
+    pub fn parse(json: &'a str) -> Result<BlockRef<'a>, ParseError> {
+        serde_json::from_str(json).map_err(Into::into)
+    }
+
+    pub fn full_text(&self) -> &str {
+        &self.full_text
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn instance(&self) -> Option<&str> {
+        self.instance.as_deref()
+    }
+
+    pub fn short_text(&self) -> Option<&str> {
+        self.short_text.as_deref()
+    }
+
+    pub fn min_width(&self) -> Option<&MinWidth> {
+        self.min_width.as_ref()
+    }
+
+    pub fn alignment(&self) -> Alignment {
+        self.alignment
+    }
+
+    pub fn markup(&self) -> Markup {
+        self.markup
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    pub fn border(&self) -> Option<Color> {
+        self.border
     }
 
-    pub fn border(&self) -> Option<&str> {
-        self.border.as_ref().map(String::as_ref)
+    pub fn background(&self) -> Option<Color> {
+        self.background
     }
 
-    pub fn background(&self) -> Option<&str> {
-        self.background.as_ref().map(String::as_ref)
+    pub fn color_str(&self) -> Option<String> {
+        self.color.map(|color| color.to_hex())
+    }
+
+    pub fn border_str(&self) -> Option<String> {
+        self.border.map(|color| color.to_hex())
+    }
+
+    pub fn background_str(&self) -> Option<String> {
+        self.background.map(|color| color.to_hex())
     }
 
     pub fn is_urgent(&self) -> bool {
@@ -92,6 +842,168 @@ impl Block {
     pub fn separator_block_width(&self) -> Option<u32> {
         self.separator_block_width
     }
+
+    pub fn border_top(&self) -> Option<u32> {
+        self.border_top
+    }
+
+    pub fn border_bottom(&self) -> Option<u32> {
+        self.border_bottom
+    }
+
+    pub fn border_left(&self) -> Option<u32> {
+        self.border_left
+    }
+
+    pub fn border_right(&self) -> Option<u32> {
+        self.border_right
+    }
+
+    pub fn extras(&self) -> &BTreeMap<String, Value> {
+        &self.extras
+    }
+
+    // True when none of the text fields had to be copied out of the input.
+    pub fn is_borrowed(&self) -> bool {
+        let borrowed = |text: &Cow<'a, str>| matches!(*text, Cow::Borrowed(_));
+
+        borrowed(&self.full_text)
+            && self.name.iter().all(borrowed)
+            && self.instance.iter().all(borrowed)
+            && self.short_text.iter().all(borrowed)
+    }
+
+    pub fn into_owned(self) -> Block {
+        Block {
+            name: self.name.map(Cow::into_owned),
+            instance: self.instance.map(Cow::into_owned),
+            full_text: self.full_text.into_owned(),
+            urgent: self.urgent,
+            separator: self.separator,
+            markup: self.markup,
+            alignment: self.alignment,
+            short_text: self.short_text.map(Cow::into_owned),
+            color: self.color,
+            background: self.background,
+            border: self.border,
+            min_width: self.min_width,
+            separator_block_width: self.separator_block_width,
+            border_top: self.border_top,
+            border_bottom: self.border_bottom,
+            border_left: self.border_left,
+            border_right: self.border_right,
+            extras: self.extras,
+        }
+    }
+}
+
+impl<'a> From<BlockRef<'a>> for Block {
+    fn from(block: BlockRef<'a>) -> Block {
+        block.into_owned()
+    }
+}
+
+impl<'a> From<Block> for BlockRef<'a> {
+    fn from(block: Block) -> BlockRef<'a> {
+        BlockRef {
+            name: block.name.map(Cow::Owned),
+            instance: block.instance.map(Cow::Owned),
+            full_text: Cow::Owned(block.full_text),
+            urgent: block.urgent,
+            separator: block.separator,
+            markup: block.markup,
+            alignment: block.alignment,
+            short_text: block.short_text.map(Cow::Owned),
+            color: block.color,
+            background: block.background,
+            border: block.border,
+            min_width: block.min_width,
+            separator_block_width: block.separator_block_width,
+            border_top: block.border_top,
+            border_bottom: block.border_bottom,
+            border_left: block.border_left,
+            border_right: block.border_right,
+            extras: block.extras,
+        }
+    }
+}
+
+// serde only borrows a bare `Cow<str>` field, not one wrapped in an `Option`.
+fn borrow_optional<'de, D>(deserializer: D) -> Result<Option<Cow<'de, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_option(OptionalCowVisitor)
+}
+
+struct CowVisitor;
+
+impl<'de> de::Visitor<'de> for CowVisitor {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_borrowed_str<E>(self, value: &'de str) -> Result<Cow<'de, str>, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Borrowed(value))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Cow<'de, str>, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Owned(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Cow<'de, str>, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Owned(value))
+    }
+}
+
+struct OptionalCowVisitor;
+
+impl<'de> de::Visitor<'de> for OptionalCowVisitor {
+    type Value = Option<Cow<'de, str>>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string or null")
+    }
+
+    fn visit_none<E>(self) -> Result<Option<Cow<'de, str>>, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_unit<E>(self) -> Result<Option<Cow<'de, str>>, E>
+    where
+        E: de::Error,
+    {
+        Ok(None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Option<Cow<'de, str>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(CowVisitor).map(Some)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BorderWidths {
+    pub top: u32,
+    pub bottom: u32,
+    pub left: u32,
+    pub right: u32,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -138,8 +1050,8 @@ impl<'de> de::Visitor<'de> for MinWidthVisitor {
     where
         E: de::Error,
     {
-        if value > ::std::u32::MAX as u64 {
-            Ok(MinWidth::from(::std::u32::MAX))
+        if value > u32::MAX as u64 {
+            Ok(MinWidth::from(u32::MAX))
         } else {
             Ok(MinWidth::from(value as u32))
         }
@@ -201,7 +1113,7 @@ mod tests {
 
         let parsed: MinWidth =
             serde_json::from_str("1234567890000").expect("Could not parse very large number");
-        assert_eq!(parsed, MinWidth::Pixels(::std::u32::MAX));
+        assert_eq!(parsed, MinWidth::Pixels(u32::MAX));
     }
 
     #[test]
@@ -224,6 +1136,277 @@ mod tests {
         assert_eq!(serde_json::to_string(&example).unwrap(), "\"Example text\"");
     }
 
+    #[test]
+    fn it_allows_building_of_blocks() {
+        let small_block = BlockBuilder::new("text").build();
+        assert_eq!(small_block.full_text(), "text");
+        assert_eq!(small_block.name(), None);
+        assert_eq!(small_block.min_width(), None);
+        assert_eq!(small_block.alignment(), Alignment::Left);
+        assert!(!small_block.is_urgent());
+        assert!(small_block.has_separator());
+
+        let big_block = BlockBuilder::new("E: 10.0.0.1 (1000 Mbit/s)")
+            .short_text("10.0.0.1")
+            .name("ethernet")
+            .instance("eth0")
+            .color(Color::rgb(0x00, 0xff, 0x00))
+            .background(Color::rgb(0x1c, 0x1c, 0x1c))
+            .border(Color::rgb(0xee, 0x00, 0x00))
+            .min_width("E: 100.100.100.100 (1000 Mbit/s)")
+            .alignment(Alignment::Right)
+            .markup(Markup::Pango)
+            .urgent(true)
+            .separator(true)
+            .separator_block_width(9)
+            .build();
+        assert_eq!(big_block.full_text(), "E: 10.0.0.1 (1000 Mbit/s)");
+        assert_eq!(big_block.short_text(), Some("10.0.0.1"));
+        assert_eq!(big_block.name(), Some("ethernet"));
+        assert_eq!(big_block.instance(), Some("eth0"));
+        assert_eq!(big_block.color(), Some(Color::rgb(0x00, 0xff, 0x00)));
+        assert_eq!(big_block.background(), Some(Color::rgb(0x1c, 0x1c, 0x1c)));
+        assert_eq!(big_block.border(), Some(Color::rgb(0xee, 0x00, 0x00)));
+        assert_eq!(
+            big_block.min_width(),
+            Some(&MinWidth::Example(String::from(
+                "E: 100.100.100.100 (1000 Mbit/s)"
+            )))
+        );
+        assert_eq!(big_block.alignment(), Alignment::Right);
+        assert_eq!(big_block.markup(), Markup::Pango);
+        assert!(big_block.is_urgent());
+        assert!(big_block.has_separator());
+        assert_eq!(big_block.separator_block_width(), Some(9));
+
+        let pixels = BlockBuilder::new("").min_width(300).build();
+        assert_eq!(pixels.min_width(), Some(&MinWidth::Pixels(300)));
+    }
+
+    #[test]
+    fn it_reports_changes_from_setters() {
+        let mut block = BlockBuilder::new("50%").name("battery").build();
+
+        assert!(!block.set_full_text("50%"));
+        assert!(block.set_full_text("49%"));
+        assert_eq!(block.full_text(), "49%");
+
+        assert!(block.set_color(Color::rgb(0xff, 0, 0)));
+        assert!(!block.set_color(Color::rgb(0xff, 0, 0)));
+        assert!(block.set_color(None));
+        assert_eq!(block.color(), None);
+
+        assert!(!block.set_name("battery"));
+        assert!(block.set_urgent(true));
+        assert!(!block.set_urgent(true));
+        assert!(block.set_min_width(MinWidth::from(100)));
+        assert!(!block.set_min_width(MinWidth::Pixels(100)));
+        assert!(block.set_alignment(Alignment::Center));
+        assert!(block.set_separator_block_width(5));
+        assert!(!block.set_separator_block_width(Some(5)));
+    }
+
+    #[test]
+    fn it_chains_with_methods() {
+        let block = BlockBuilder::new("old")
+            .build()
+            .with_full_text("new")
+            .with_short_text("n")
+            .with_markup(Markup::Pango)
+            .with_separator(true)
+            .with_min_width(None);
+
+        assert_eq!(block.full_text(), "new");
+        assert_eq!(block.short_text(), Some("n"));
+        assert_eq!(block.markup(), Markup::Pango);
+        assert!(block.has_separator());
+        assert_eq!(block.min_width(), None);
+    }
+
+    #[test]
+    fn it_roundtrips_custom_fields() {
+        let json = r#"{"full_text":"x","_module_id":7,"_tags":["a","b"],"unknown":true}"#;
+        let mut block: Block = json.parse().expect("Could not parse");
+
+        assert_eq!(block.extras().len(), 2);
+        assert_eq!(block.extra("_module_id"), Some(&Value::from(7)));
+        assert_eq!(block.extra("unknown"), None);
+        assert_eq!(block.get_extra::<u32>("_module_id").unwrap(), Some(7));
+        assert_eq!(
+            block.get_extra::<Vec<String>>("_tags").unwrap(),
+            Some(vec![String::from("a"), String::from("b")])
+        );
+        assert!(block.get_extra::<String>("_module_id").is_err());
+        assert_eq!(block.get_extra::<u32>("_missing").unwrap(), None);
+
+        assert!(block.set_extra("_module_id", 8).unwrap());
+        assert!(!block.set_extra("_module_id", 8).unwrap());
+        assert!(block.set_extra("no_underscore", 1).is_err());
+        assert_eq!(
+            block.remove_extra("_tags"),
+            Some(Value::from(vec!["a", "b"]))
+        );
+
+        let serialized = serde_json::to_string(&block).unwrap();
+        let reparsed: Block = serialized.parse().unwrap();
+        assert_eq!(reparsed.extras(), block.extras());
+        assert!(serialized.contains(r#""_module_id":8"#));
+        assert!(!serialized.contains("unknown"));
+    }
+
+    #[test]
+    fn it_handles_sway_border_widths() {
+        let json = r##"{"full_text":"x","border":"#ff0000","border_top":0,"border_bottom":3}"##;
+        let block: Block = json.parse().expect("Could not parse");
+
+        assert_eq!(block.border_top(), Some(0));
+        assert_eq!(block.border_bottom(), Some(3));
+        assert_eq!(block.border_left(), None);
+        assert_eq!(
+            block.border_widths(),
+            BorderWidths {
+                top: 0,
+                bottom: 3,
+                left: 1,
+                right: 1,
+            }
+        );
+
+        let reparsed: Block = serde_json::to_string(&block).unwrap().parse().unwrap();
+        assert_eq!(reparsed.border_top(), Some(0));
+        assert_eq!(reparsed.border_bottom(), Some(3));
+        assert_eq!(reparsed.border_right(), None);
+
+        let block = BlockBuilder::new("x")
+            .border_bottom(2)
+            .border_left(0)
+            .border_right(0)
+            .border_top(0)
+            .build();
+        assert_eq!(block.border_widths(), BorderWidths::default());
+        assert_eq!(
+            block.with_border(Color::rgb(0, 0, 0xff)).border_widths(),
+            BorderWidths {
+                top: 0,
+                bottom: 2,
+                left: 0,
+                right: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn it_borrows_unescaped_strings() {
+        let json =
+            r##"{"full_text":"50%","name":"battery","instance":"a\"b","color":"#ff0000","_id":3}"##;
+        let block = BlockRef::parse(json).expect("Could not parse");
+
+        assert!(matches!(block.full_text, Cow::Borrowed("50%")));
+        assert!(matches!(block.name, Some(Cow::Borrowed("battery"))));
+        assert!(matches!(block.instance, Some(Cow::Owned(_))));
+        assert_eq!(block.instance(), Some("a\"b"));
+        assert!(block.short_text().is_none());
+        assert_eq!(block.color_str().as_deref(), Some("#ff0000"));
+        assert!(!block.is_borrowed());
+
+        let owned = block.into_owned();
+        assert_eq!(owned, json.parse::<Block>().unwrap());
+        assert_eq!(owned.color().unwrap(), "#ff0000");
+        assert_eq!(owned.extra("_id"), Some(&Value::from(3)));
+        assert_eq!(Block::from(BlockRef::from(owned.clone())), owned);
+    }
+
+    #[test]
+    fn it_serializes_compactly() {
+        let block = BlockBuilder::new("50%").build();
+        assert_eq!(
+            block.to_string(),
+            r#"{"full_text":"50%","separator":true}"#
+        );
+
+        let mut block = BlockBuilder::new("50%")
+            .name("battery")
+            .urgent(true)
+            .separator(true)
+            .alignment(Alignment::Right)
+            .color(Color::rgb(0xff, 0, 0))
+            .border_top(2)
+            .build();
+        block.set_extra("_id", 1).unwrap();
+        let json = r##"{"name":"battery","full_text":"50%","urgent":true,"separator":true,"align":"right","color":"#ff0000","border_top":2,"_id":1}"##;
+        assert_eq!(block.to_string(), json);
+        assert_eq!(json.parse::<Block>().unwrap(), block);
+    }
+
+    #[test]
+    fn it_sets_pango_markup() {
+        let title = "Rock & Roll <Live>";
+        let block = BlockBuilder::new("")
+            .pango_markup(PangoMarkup::new().bold("Now playing: ").text(title))
+            .build();
+
+        assert_eq!(block.markup(), Markup::Pango);
+        assert_eq!(
+            block.full_text(),
+            "<b>Now playing: </b>Rock &amp; Roll &lt;Live&gt;"
+        );
+        assert!(block.validate().is_empty());
+
+        let mut block = BlockBuilder::new("plain").build();
+        assert!(block.set_pango_markup(PangoMarkup::from("plain")));
+        assert_eq!(block.markup(), Markup::Pango);
+        assert!(!block.set_pango_markup(PangoMarkup::from("plain")));
+    }
+
+    #[test]
+    fn it_parses_text_runs() {
+        let block = BlockBuilder::new("<b>a</b> &amp; b")
+            .short_text("<i>a")
+            .markup(Markup::Pango)
+            .build();
+
+        let runs = block.full_text_runs().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].text, "a");
+        assert_eq!(runs[1].text, " & b");
+        assert!(block.short_text_runs().unwrap().is_err());
+
+        let block = BlockBuilder::new("<b>a</b> &amp; b").build();
+        let runs = block.full_text_runs().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].text, "<b>a</b> &amp; b");
+        assert!(block.short_text_runs().is_none());
+    }
+
+    #[test]
+    fn it_extracts_plain_text() {
+        let block = BlockBuilder::new(
+            "<span foreground='#ff0000'>CPU <span weight=\"bold\">5%</span></span> &amp; \
+             &#8593;&#x2193; <small>&lt;idle&gt;</small>",
+        )
+        .short_text("<b>5%</b>")
+        .markup(Markup::Pango)
+        .build();
+
+        assert_eq!(
+            block.plain_full_text().unwrap(),
+            "CPU 5% & \u{2191}\u{2193} <idle>"
+        );
+        assert_eq!(block.plain_short_text().unwrap().unwrap(), "5%");
+
+        let block = block
+            .with_full_text("<b>5%")
+            .with_short_text::<_, String>(None);
+        assert!(block.plain_full_text().is_err());
+        assert!(block.plain_short_text().is_none());
+
+        let block = BlockBuilder::new("<b>5%</b> &amp;").build();
+        assert!(matches!(
+            block.plain_full_text(),
+            Ok(Cow::Borrowed("<b>5%</b> &amp;"))
+        ));
+    }
+
     #[test]
     fn it_parses_minimal_block() {
         let json = r#"{"full_text":"E: 10.0.0.1 (1000 Mbit/s)"}"#;
@@ -238,6 +1421,24 @@ mod tests {
         assert_eq!(block.min_width(), None);
     }
 
+    #[test]
+    fn it_rejects_invalid_colors() {
+        match Block::from_str(r#"{"full_text":"x","color":"red"}"#) {
+            Err(ParseError::InvalidData(_)) => {}
+            other => panic!("Expected invalid data, got {:?}", other),
+        }
+        assert!(Block::from_str(r##"{"full_text":"x","border":"#12345"}"##).is_err());
+
+        let block = Block::from_str(r##"{"full_text":"x","background":"#AABBCC80"}"##).unwrap();
+        assert_eq!(
+            block.background(),
+            Some(Color::rgba(0xaa, 0xbb, 0xcc, 0x80))
+        );
+        assert!(serde_json::to_string(&block)
+            .unwrap()
+            .contains(r##""background":"#aabbcc80""##));
+    }
+
     #[test]
     fn it_parses_full_block() {
         let json = r##"
@@ -260,15 +1461,19 @@ mod tests {
 
         assert_eq!(block.full_text(), "E: 10.0.0.1 (1000 Mbit/s)");
         assert_eq!(block.short_text(), Some("10.0.0.1"));
-        assert_eq!(block.color(), Some("#00ff00"));
-        assert_eq!(block.background(), Some("#1c1c1c"));
-        assert_eq!(block.border(), Some("#ee0000"));
+        assert_eq!(block.color(), Some(Color::rgb(0x00, 0xff, 0x00)));
+        assert_eq!(block.background(), Some(Color::rgb(0x1c, 0x1c, 0x1c)));
+        assert_eq!(block.border(), Some(Color::rgb(0xee, 0x00, 0x00)));
+        assert!(block.color().unwrap() == "#00ff00");
+        assert_eq!(block.color_str().as_deref(), Some("#00ff00"));
+        assert_eq!(block.background_str().as_deref(), Some("#1c1c1c"));
+        assert_eq!(block.border_str().as_deref(), Some("#ee0000"));
         assert_eq!(block.min_width(), Some(&MinWidth::Pixels(300)));
         assert_eq!(block.alignment(), Alignment::Right);
-        assert_eq!(block.is_urgent(), false);
+        assert!(!block.is_urgent());
         assert_eq!(block.name(), Some("ethernet"));
         assert_eq!(block.instance(), Some("eth0"));
-        assert_eq!(block.has_separator(), true);
+        assert!(block.has_separator());
         assert_eq!(block.separator_block_width(), Some(9));
     }
 }
@@ -2,7 +2,8 @@ extern crate serde_json;
 
 use std::fmt;
 use std::str::FromStr;
-use super::{MouseButton, ParseError};
+use super::{MouseButton, ParseError, ParseMode};
+use mode;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug)]
 pub struct ClickEvent {
@@ -22,6 +23,10 @@ impl FromStr for ClickEvent {
 }
 
 impl ClickEvent {
+    pub fn from_str_mode(str: &str, mode: ParseMode) -> Result<ClickEvent, ParseError> {
+        mode::parse(str, &mode::CLICK_EVENT, mode)
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -67,7 +72,7 @@ impl ClickEventBuilder {
     {
         ClickEventBuilder {
             name: name.into(),
-            button: button,
+            button,
             instance: None,
             x: None,
             y: None,
@@ -0,0 +1,376 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use super::ParseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color {
+    red: u8,
+    green: u8,
+    blue: u8,
+    alpha: u8,
+}
+
+impl Color {
+    pub fn rgb(red: u8, green: u8, blue: u8) -> Color {
+        Color::rgba(red, green, blue, 0xff)
+    }
+
+    pub fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Color {
+        Color {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    pub fn red(&self) -> u8 {
+        self.red
+    }
+
+    pub fn green(&self) -> u8 {
+        self.green
+    }
+
+    pub fn blue(&self) -> u8 {
+        self.blue
+    }
+
+    pub fn alpha(&self) -> u8 {
+        self.alpha
+    }
+
+    pub fn components(&self) -> (u8, u8, u8, u8) {
+        (self.red, self.green, self.blue, self.alpha)
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.alpha == 0xff
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn with_alpha(&self, alpha: u8) -> Color {
+        Color::rgba(self.red, self.green, self.blue, alpha)
+    }
+
+    // Linear interpolation of every channel; a ratio of 0 gives self and 1 gives other.
+    pub fn mix(&self, other: Color, ratio: f64) -> Color {
+        let ratio = clamp_unit(ratio);
+        let channel = |from: u8, to: u8| {
+            (f64::from(from) + (f64::from(to) - f64::from(from)) * ratio).round() as u8
+        };
+
+        Color::rgba(
+            channel(self.red, other.red),
+            channel(self.green, other.green),
+            channel(self.blue, other.blue),
+            channel(self.alpha, other.alpha),
+        )
+    }
+
+    pub fn lighten(&self, amount: f64) -> Color {
+        self.mix(Color::rgba(0xff, 0xff, 0xff, self.alpha), amount)
+    }
+
+    pub fn darken(&self, amount: f64) -> Color {
+        self.mix(Color::rgba(0x00, 0x00, 0x00, self.alpha), amount)
+    }
+
+    // Source-over alpha compositing of self on top of background.
+    pub fn over(&self, background: Color) -> Color {
+        let top_alpha = f64::from(self.alpha) / 255.0;
+        let bottom_alpha = f64::from(background.alpha) / 255.0;
+        let alpha = top_alpha + bottom_alpha * (1.0 - top_alpha);
+        if alpha == 0.0 {
+            return Color::rgba(0, 0, 0, 0);
+        }
+
+        let channel = |top: u8, bottom: u8| {
+            let value = (f64::from(top) * top_alpha
+                + f64::from(bottom) * bottom_alpha * (1.0 - top_alpha))
+                / alpha;
+            value.round() as u8
+        };
+
+        Color::rgba(
+            channel(self.red, background.red),
+            channel(self.green, background.green),
+            channel(self.blue, background.blue),
+            (alpha * 255.0).round() as u8,
+        )
+    }
+
+    // Relative luminance as defined by WCAG 2, ignoring alpha.
+    pub fn luminance(&self) -> f64 {
+        let channel = |value: u8| {
+            let value = f64::from(value) / 255.0;
+            if value <= 0.03928 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        0.2126 * channel(self.red) + 0.7152 * channel(self.green) + 0.0722 * channel(self.blue)
+    }
+
+    // WCAG contrast ratio between 1 and 21. A translucent self is composited over the
+    // background first, like it would be when drawn.
+    pub fn contrast_ratio(&self, background: Color) -> f64 {
+        let foreground = self.over(background).luminance();
+        let background = background.luminance();
+        let (lighter, darker) = if foreground > background {
+            (foreground, background)
+        } else {
+            (background, foreground)
+        };
+
+        (lighter + 0.05) / (darker + 0.05)
+    }
+
+    pub fn most_readable_on(background: Color, candidates: &[Color]) -> Option<Color> {
+        candidates
+            .iter()
+            .cloned()
+            .fold(None, |best: Option<Color>, candidate| match best {
+                Some(best)
+                    if best.contrast_ratio(background) >= candidate.contrast_ratio(background) =>
+                {
+                    Some(best)
+                }
+                _ => Some(candidate),
+            })
+    }
+
+    // Black or white, whichever is easier to read on self as a background.
+    pub fn readable_foreground(&self) -> Color {
+        let black = Color::rgb(0x00, 0x00, 0x00);
+        let white = Color::rgb(0xff, 0xff, 0xff);
+        Color::most_readable_on(*self, &[black, white]).unwrap_or(white)
+    }
+}
+
+fn clamp_unit(value: f64) -> f64 {
+    if value.is_nan() {
+        0.0
+    } else {
+        value.clamp(0.0, 1.0)
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> Result<Color, ParseError> {
+        let invalid = || {
+            ParseError::InvalidData(format!(
+                "Invalid color {:?}, expected #RRGGBB or #RRGGBBAA",
+                str
+            ))
+        };
+
+        let hex = match str.strip_prefix('#') {
+            Some(hex) if hex.len() == 6 || hex.len() == 8 => hex,
+            _ => return Err(invalid()),
+        };
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+
+        let component =
+            |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| invalid());
+        let alpha = if hex.len() == 8 { component(6)? } else { 0xff };
+
+        Ok(Color::rgba(
+            component(0)?,
+            component(2)?,
+            component(4)?,
+            alpha,
+        ))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)?;
+        if !self.is_opaque() {
+            write!(f, "{:02x}", self.alpha)?;
+        }
+        Ok(())
+    }
+}
+
+// Allows comparing against the hex strings that used to be returned for block colors.
+impl PartialEq<str> for Color {
+    fn eq(&self, other: &str) -> bool {
+        other.parse::<Color>().ok() == Some(*self)
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Color {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+struct ColorVisitor;
+
+impl<'de> de::Visitor<'de> for ColorVisitor {
+    type Value = Color;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a color in #RRGGBB or #RRGGBBAA format")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Color, E>
+    where
+        E: de::Error,
+    {
+        value
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Color, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(ColorVisitor)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate serde_json;
+
+    use super::*;
+
+    #[test]
+    fn it_parses_colors() {
+        let color: Color = "#00ff7F".parse().expect("Could not parse RGB");
+        assert_eq!(color.components(), (0x00, 0xff, 0x7f, 0xff));
+        assert!(color.is_opaque());
+
+        let color: Color = "#11223344".parse().expect("Could not parse RGBA");
+        assert_eq!(color, Color::rgba(0x11, 0x22, 0x33, 0x44));
+        assert_eq!(color.alpha(), 0x44);
+    }
+
+    #[test]
+    fn it_rejects_invalid_colors() {
+        for input in &[
+            "red", "#12345", "#1234567", "00ff00", "#00ff0g", "#ff", "", "#+1+2+3",
+        ] {
+            match input.parse::<Color>() {
+                Err(ParseError::InvalidData(_)) => {}
+                other => panic!("Expected {:?} to be rejected, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn it_formats_canonical_hex() {
+        assert_eq!(Color::rgb(0xAB, 0x0C, 0xff).to_string(), "#ab0cff");
+        assert_eq!(Color::rgba(1, 2, 3, 4).to_hex(), "#01020304");
+        assert_eq!(Color::rgba(1, 2, 3, 0xff).to_hex(), "#010203");
+        assert!(Color::rgb(0, 0xff, 0) == "#00FF00");
+    }
+
+    #[test]
+    fn it_mixes_colors() {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(0xff, 0xff, 0xff);
+
+        assert_eq!(black.mix(white, 0.0), black);
+        assert_eq!(black.mix(white, 1.0), white);
+        assert_eq!(black.mix(white, 0.5), Color::rgb(0x80, 0x80, 0x80));
+        assert_eq!(black.mix(white, 7.0), white);
+        assert_eq!(
+            Color::rgba(0, 0, 0, 0).mix(Color::rgba(100, 200, 50, 200), 0.5),
+            Color::rgba(50, 100, 25, 100)
+        );
+
+        let red = Color::rgb(0xcc, 0x00, 0x00);
+        assert_eq!(red.lighten(0.5), Color::rgb(0xe6, 0x80, 0x80));
+        assert_eq!(red.darken(0.5), Color::rgb(0x66, 0x00, 0x00));
+        assert_eq!(red.with_alpha(0x80).darken(1.0), Color::rgba(0, 0, 0, 0x80));
+    }
+
+    #[test]
+    fn it_composites_over_backgrounds() {
+        let background = Color::rgb(0x00, 0x00, 0xff);
+
+        assert_eq!(
+            Color::rgb(0xff, 0, 0).over(background),
+            Color::rgb(0xff, 0, 0)
+        );
+        assert_eq!(Color::rgba(0xff, 0, 0, 0).over(background), background);
+        assert_eq!(
+            Color::rgba(0xff, 0, 0, 0x80).over(background),
+            Color::rgb(0x80, 0x00, 0x7f)
+        );
+        assert_eq!(
+            Color::rgba(0, 0, 0, 0).over(Color::rgba(0, 0, 0, 0)),
+            Color::rgba(0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn it_calculates_contrast() {
+        let black = Color::rgb(0, 0, 0);
+        let white = Color::rgb(0xff, 0xff, 0xff);
+
+        assert!((black.contrast_ratio(white) - 21.0).abs() < 1e-9);
+        assert!((white.contrast_ratio(white) - 1.0).abs() < 1e-9);
+        assert!((black.contrast_ratio(white) - white.contrast_ratio(black)).abs() < 1e-9);
+
+        let gray = Color::rgb(0x77, 0x77, 0x77);
+        assert!((gray.contrast_ratio(white) - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn it_selects_readable_foregrounds() {
+        assert_eq!(
+            Color::rgb(0x1c, 0x1c, 0x1c).readable_foreground(),
+            Color::rgb(0xff, 0xff, 0xff)
+        );
+        assert_eq!(
+            Color::rgb(0xee, 0xee, 0x00).readable_foreground(),
+            Color::rgb(0x00, 0x00, 0x00)
+        );
+
+        let background = Color::rgb(0x20, 0x20, 0x20);
+        let candidates = [Color::rgb(0x30, 0x30, 0x30), Color::rgb(0xaa, 0xaa, 0xaa)];
+        assert_eq!(
+            Color::most_readable_on(background, &candidates),
+            Some(candidates[1])
+        );
+        assert_eq!(Color::most_readable_on(background, &[]), None);
+    }
+
+    #[test]
+    fn it_serdes_colors() {
+        let color: Color = serde_json::from_str("\"#EE0000\"").expect("Could not parse");
+        assert_eq!(serde_json::to_string(&color).unwrap(), "\"#ee0000\"");
+
+        let parsed: Result<Color, _> = serde_json::from_str("\"red\"");
+        assert!(parsed.is_err());
+        let parsed: Result<Color, _> = serde_json::from_str("12");
+        assert!(parsed.is_err());
+    }
+}
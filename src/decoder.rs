@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+use super::{Block, ClickEvent, Header, ParseError, ParseMode};
+use framing::{ArrayFraming, Frame, LineBuffer, StatusLineFraming};
+
+#[derive(Debug)]
+pub enum Decoded {
+    Header(Header),
+    StatusLine(Vec<Block>),
+    ClickEvent(ClickEvent),
+}
+
+#[derive(Debug, Clone)]
+enum Stream {
+    StatusLines(StatusLineFraming),
+    ClickEvents(ArrayFraming),
+}
+
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    lines: LineBuffer,
+    stream: Stream,
+}
+
+impl Decoder {
+    pub fn status_lines() -> Decoder {
+        Decoder::status_lines_with_mode(ParseMode::Normal)
+    }
+
+    pub fn status_lines_with_mode(mode: ParseMode) -> Decoder {
+        Decoder {
+            lines: LineBuffer::new(),
+            stream: Stream::StatusLines(StatusLineFraming::with_mode(mode)),
+        }
+    }
+
+    pub fn click_events() -> Decoder {
+        Decoder::click_events_with_mode(ParseMode::Normal)
+    }
+
+    pub fn click_events_with_mode(mode: ParseMode) -> Decoder {
+        Decoder {
+            lines: LineBuffer::new(),
+            stream: Stream::ClickEvents(ArrayFraming::click_events(mode)),
+        }
+    }
+
+    pub fn is_plain_text(&self) -> bool {
+        match self.stream {
+            Stream::StatusLines(ref framing) => framing.is_plain_text(),
+            Stream::ClickEvents(_) => false,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self.stream {
+            Stream::StatusLines(ref framing) => framing.is_closed(),
+            Stream::ClickEvents(ref framing) => framing.is_closed(),
+        }
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<Result<Decoded, ParseError>> {
+        self.lines.extend(bytes);
+
+        let mut decoded = Vec::new();
+        while let Some(line) = self.lines.next_line() {
+            match line {
+                Ok(line) => self.decode_line(&line, &mut decoded),
+                Err(error) => decoded.push(Err(error)),
+            }
+        }
+        decoded
+    }
+
+    // Decodes a final line that was not terminated by a newline before the end of the stream.
+    pub fn finish(&mut self) -> Vec<Result<Decoded, ParseError>> {
+        let mut decoded = Vec::new();
+        if let Some(line) = self.lines.take_remaining() {
+            self.decode_line(&line, &mut decoded);
+        }
+
+        let error = match self.stream {
+            Stream::StatusLines(ref mut framing) => framing.finish(),
+            Stream::ClickEvents(ref mut framing) => framing.finish(),
+        };
+        decoded.extend(error.map(Err));
+        decoded
+    }
+
+    fn decode_line(&mut self, line: &str, decoded: &mut Vec<Result<Decoded, ParseError>>) {
+        match self.stream {
+            Stream::StatusLines(ref mut framing) => {
+                let mut frames = VecDeque::new();
+                framing.push_line(line, &mut frames);
+                decoded.extend(frames.into_iter().map(|frame| {
+                    frame.map(|frame| match frame {
+                        Frame::Header(header) => Decoded::Header(header),
+                        Frame::StatusLine(blocks) => Decoded::StatusLine(blocks),
+                    })
+                }));
+            }
+            Stream::ClickEvents(ref mut framing) => {
+                framing.elements(line, |event| decoded.push(event.map(Decoded::ClickEvent)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::MouseButton;
+
+    #[test]
+    fn it_decodes_status_lines_split_across_chunks() {
+        let mut decoder = Decoder::status_lines();
+
+        assert!(decoder.feed(b"{\"version\":1,\"click").is_empty());
+        match decoder.feed(b"_events\":true}\n[\n[{\"full_").as_slice() {
+            [Ok(Decoded::Header(header))] => assert!(header.click_events()),
+            other => panic!("Unexpected values: {:?}", other),
+        }
+        match decoder
+            .feed(b"text\":\"a\"}]\n,[]\n,[{\"full_text\":\"b\"}]")
+            .as_slice()
+        {
+            [Ok(Decoded::StatusLine(first)), Ok(Decoded::StatusLine(second))] => {
+                assert_eq!(first[0].full_text(), "a");
+                assert!(second.is_empty());
+            }
+            other => panic!("Unexpected values: {:?}", other),
+        }
+        match decoder.finish().as_slice() {
+            [Ok(Decoded::StatusLine(blocks))] => assert_eq!(blocks[0].full_text(), "b"),
+            other => panic!("Unexpected values: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_keeps_reading_json_after_a_strict_header_error() {
+        let mut decoder = Decoder::status_lines_with_mode(ParseMode::Strict);
+
+        match decoder
+            .feed(b"{\"version\":1,\"clicks\":true}\n[\n[{\"full_text\":\"a\"}]\n")
+            .as_slice()
+        {
+            [Err(error), Ok(Decoded::StatusLine(blocks))] => {
+                assert_eq!(error.raw_line(), Some("{\"version\":1,\"clicks\":true}"));
+                assert_eq!(blocks.len(), 1);
+                assert_eq!(blocks[0].full_text(), "a");
+            }
+            other => panic!("Unexpected values: {:?}", other),
+        }
+        assert!(!decoder.is_plain_text());
+    }
+
+    #[test]
+    fn it_decodes_click_events() {
+        let mut decoder = Decoder::click_events();
+
+        assert!(decoder.feed(b"[\n{\"name\":\"a\",").is_empty());
+        match decoder.feed(b"\"button\":2}\nnonsense\n").as_slice() {
+            [Ok(Decoded::ClickEvent(event)), Err(error)] => {
+                assert_eq!(event.name(), "a");
+                assert_eq!(event.button(), MouseButton::Middle);
+                assert_eq!(error.raw_line(), Some("nonsense"));
+            }
+            other => panic!("Unexpected values: {:?}", other),
+        }
+        assert!(!decoder.is_closed());
+        assert!(decoder.feed(b"]\n").is_empty());
+        assert!(decoder.is_closed());
+    }
+}
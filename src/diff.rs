@@ -0,0 +1,370 @@
+extern crate serde_json;
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use serde_json::{Map, Value};
+use super::Block;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Field {
+    Name,
+    Instance,
+    FullText,
+    ShortText,
+    Color,
+    Background,
+    Border,
+    BorderTop,
+    BorderBottom,
+    BorderLeft,
+    BorderRight,
+    MinWidth,
+    Alignment,
+    Urgent,
+    Separator,
+    SeparatorBlockWidth,
+    Markup,
+    Extra(String),
+}
+
+impl Field {
+    pub fn from_key(key: &str) -> Field {
+        match key {
+            "name" => Field::Name,
+            "instance" => Field::Instance,
+            "full_text" => Field::FullText,
+            "short_text" => Field::ShortText,
+            "color" => Field::Color,
+            "background" => Field::Background,
+            "border" => Field::Border,
+            "border_top" => Field::BorderTop,
+            "border_bottom" => Field::BorderBottom,
+            "border_left" => Field::BorderLeft,
+            "border_right" => Field::BorderRight,
+            "min_width" => Field::MinWidth,
+            "align" => Field::Alignment,
+            "urgent" => Field::Urgent,
+            "separator" => Field::Separator,
+            "separator_block_width" => Field::SeparatorBlockWidth,
+            "markup" => Field::Markup,
+            other => Field::Extra(other.to_owned()),
+        }
+    }
+}
+
+// Values are given in their JSON form. Fields with a default value are given that value when they
+// are left at it, so null only stands for optional fields that are not set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: Field,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockChanges {
+    pub old_index: usize,
+    pub new_index: usize,
+    pub fields: Vec<FieldChange>,
+}
+
+// Blocks are matched by (name, instance); repeated keys, including blocks without a name, are
+// matched in order of appearance. Indexes in `removed` refer to the old line and indexes in
+// `added` to the new one. Only the fewest blocks needed to explain a reordering are reported as
+// moved.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StatusLineDiff {
+    added: Vec<usize>,
+    removed: Vec<usize>,
+    moved: Vec<Move>,
+    changed: Vec<BlockChanges>,
+}
+
+impl StatusLineDiff {
+    pub fn new(old: &[Block], new: &[Block]) -> StatusLineDiff {
+        let mut candidates: HashMap<(Option<&str>, Option<&str>), VecDeque<usize>> = HashMap::new();
+        for (index, block) in new.iter().enumerate() {
+            candidates
+                .entry((block.name(), block.instance()))
+                .or_default()
+                .push_back(index);
+        }
+
+        let mut diff = StatusLineDiff::default();
+        let mut pairs = Vec::new();
+        let mut matched = BTreeSet::new();
+
+        for (old_index, block) in old.iter().enumerate() {
+            let candidate = candidates
+                .get_mut(&(block.name(), block.instance()))
+                .and_then(VecDeque::pop_front);
+
+            match candidate {
+                Some(new_index) => {
+                    matched.insert(new_index);
+                    pairs.push((old_index, new_index));
+                }
+                None => diff.removed.push(old_index),
+            }
+        }
+
+        diff.added = (0..new.len())
+            .filter(|index| !matched.contains(index))
+            .collect();
+
+        let in_order = longest_increasing(&pairs);
+        for (position, &(old_index, new_index)) in pairs.iter().enumerate() {
+            if !in_order.contains(&position) {
+                diff.moved.push(Move {
+                    from: old_index,
+                    to: new_index,
+                });
+            }
+
+            let fields = field_changes(&old[old_index], &new[new_index]);
+            if !fields.is_empty() {
+                diff.changed.push(BlockChanges {
+                    old_index,
+                    new_index,
+                    fields,
+                });
+            }
+        }
+
+        diff
+    }
+
+    pub fn added(&self) -> &[usize] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[usize] {
+        &self.removed
+    }
+
+    pub fn moved(&self) -> &[Move] {
+        &self.moved
+    }
+
+    pub fn changed(&self) -> &[BlockChanges] {
+        &self.changed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.moved.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+pub fn field_changes(old: &Block, new: &Block) -> Vec<FieldChange> {
+    if old == new {
+        return Vec::new();
+    }
+
+    let old = to_object(old);
+    let new = to_object(new);
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    let mut changes: Vec<FieldChange> = keys
+        .into_iter()
+        .filter_map(|key| {
+            let old_value = old.get(key).cloned().unwrap_or(Value::Null);
+            let new_value = new.get(key).cloned().unwrap_or(Value::Null);
+            if old_value == new_value {
+                None
+            } else {
+                Some(FieldChange {
+                    field: Field::from_key(key),
+                    old: old_value,
+                    new: new_value,
+                })
+            }
+        })
+        .collect();
+
+    changes.sort_by(|a, b| a.field.cmp(&b.field));
+    changes
+}
+
+// Serialization leaves out default values, so those are put back in.
+fn to_object(block: &Block) -> Map<String, Value> {
+    let mut map = match serde_json::to_value(block) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+
+    let defaults = [
+        ("urgent", Value::from(block.is_urgent())),
+        (
+            "markup",
+            serde_json::to_value(block.markup()).unwrap_or(Value::Null),
+        ),
+        (
+            "align",
+            serde_json::to_value(block.alignment()).unwrap_or(Value::Null),
+        ),
+    ];
+    for (key, value) in defaults {
+        map.entry(key).or_insert(value);
+    }
+    map
+}
+
+// Positions in `pairs` (sorted by old index) that form the longest run of increasing new
+// indexes; every other pair had to move.
+fn longest_increasing(pairs: &[(usize, usize)]) -> BTreeSet<usize> {
+    let mut lengths = vec![1; pairs.len()];
+    let mut previous = vec![None; pairs.len()];
+
+    for i in 0..pairs.len() {
+        for j in 0..i {
+            if pairs[j].1 < pairs[i].1 && lengths[j] + 1 > lengths[i] {
+                lengths[i] = lengths[j] + 1;
+                previous[i] = Some(j);
+            }
+        }
+    }
+
+    let mut result = BTreeSet::new();
+    let mut current = (0..pairs.len()).max_by_key(|&i| lengths[i]);
+    while let Some(i) = current {
+        result.insert(i);
+        current = previous[i];
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Alignment, BlockBuilder, Color, Markup};
+
+    fn block(name: &str, text: &str) -> Block {
+        BlockBuilder::new(text).name(name).build()
+    }
+
+    #[test]
+    fn it_finds_no_changes_in_identical_lines() {
+        let line = vec![block("a", "1"), block("b", "2")];
+        assert!(StatusLineDiff::new(&line, &line.clone()).is_empty());
+    }
+
+    #[test]
+    fn it_finds_added_and_removed_blocks() {
+        let old = vec![block("a", "1"), block("b", "2"), block("c", "3")];
+        let new = vec![block("a", "1"), block("c", "3"), block("d", "4")];
+        let diff = StatusLineDiff::new(&old, &new);
+
+        assert_eq!(diff.removed(), &[1]);
+        assert_eq!(diff.added(), &[2]);
+        assert!(diff.moved().is_empty());
+        assert!(diff.changed().is_empty());
+    }
+
+    #[test]
+    fn it_finds_moved_blocks() {
+        let old = vec![
+            block("a", "1"),
+            block("b", "2"),
+            block("c", "3"),
+            block("d", "4"),
+        ];
+        let new = vec![
+            block("d", "4"),
+            block("a", "1"),
+            block("b", "2"),
+            block("c", "3"),
+        ];
+        let diff = StatusLineDiff::new(&old, &new);
+
+        assert_eq!(diff.moved(), &[Move { from: 3, to: 0 }]);
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+    }
+
+    #[test]
+    fn it_finds_field_changes() {
+        let old = vec![
+            BlockBuilder::new("50%").name("battery").build(),
+            BlockBuilder::new("unnamed").build(),
+        ];
+        let mut new = old.clone();
+        new[0].set_full_text("49%");
+        new[0].set_color(Color::rgb(0xff, 0, 0));
+        new[1].set_extra("_id", 4).unwrap();
+
+        let diff = StatusLineDiff::new(&old, &new);
+        assert_eq!(diff.changed().len(), 2);
+
+        let battery = &diff.changed()[0];
+        assert_eq!((battery.old_index, battery.new_index), (0, 0));
+        assert_eq!(
+            battery.fields,
+            vec![
+                FieldChange {
+                    field: Field::FullText,
+                    old: Value::from("50%"),
+                    new: Value::from("49%"),
+                },
+                FieldChange {
+                    field: Field::Color,
+                    old: Value::Null,
+                    new: Value::from("#ff0000"),
+                },
+            ]
+        );
+
+        assert_eq!(
+            diff.changed()[1].fields[0].field,
+            Field::Extra(String::from("_id"))
+        );
+    }
+
+    #[test]
+    fn it_reports_default_values() {
+        let old = BlockBuilder::new("a")
+            .urgent(true)
+            .markup(Markup::Pango)
+            .build();
+        let new = BlockBuilder::new("a").alignment(Alignment::Right).build();
+
+        assert_eq!(
+            field_changes(&old, &new),
+            vec![
+                FieldChange {
+                    field: Field::Alignment,
+                    old: Value::from("left"),
+                    new: Value::from("right"),
+                },
+                FieldChange {
+                    field: Field::Urgent,
+                    old: Value::from(true),
+                    new: Value::from(false),
+                },
+                FieldChange {
+                    field: Field::Markup,
+                    old: Value::from("pango"),
+                    new: Value::from("none"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_matches_repeated_keys_in_order() {
+        let old = vec![block("cpu", "1"), block("cpu", "2")];
+        let new = vec![block("cpu", "1"), block("cpu", "3"), block("cpu", "4")];
+        let diff = StatusLineDiff::new(&old, &new);
+
+        assert_eq!(diff.added(), &[2]);
+        assert_eq!(diff.changed().len(), 1);
+        assert_eq!(diff.changed()[0].new_index, 1);
+    }
+}
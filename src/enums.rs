@@ -72,20 +72,22 @@ impl Serialize for MouseButton {
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub enum Markup {
+    #[default]
     None,
     Pango,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
 pub enum Alignment {
+    #[default]
     Left,
     Center,
     Right,
 }
 
-const VALID_MARKUPS: &'static [&'static str] = &["pango", "none"];
+const VALID_MARKUPS: &[&str] = &["pango", "none"];
 
 impl Markup {
     fn from_symbol(symbol: &str) -> Option<Markup> {
@@ -104,13 +106,7 @@ impl Markup {
     }
 }
 
-impl Default for Markup {
-    fn default() -> Markup {
-        Markup::None
-    }
-}
-
-const VALID_ALIGNMENTS: &'static [&'static str] = &["left", "center", "right"];
+const VALID_ALIGNMENTS: &[&str] = &["left", "center", "right"];
 
 impl Alignment {
     fn from_symbol(symbol: &str) -> Option<Alignment> {
@@ -131,12 +127,6 @@ impl Alignment {
     }
 }
 
-impl Default for Alignment {
-    fn default() -> Alignment {
-        Alignment::Left
-    }
-}
-
 struct MarkupVisitor;
 struct AlignmentVisitor;
 
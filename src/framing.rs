@@ -0,0 +1,713 @@
+extern crate serde_json;
+
+use std::collections::VecDeque;
+use std::mem;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use serde_json::Value;
+use super::{Block, BlockBuilder, Header, ParseError, ParseMode};
+use mode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    BeforeArray,
+    InArray,
+    Closed,
+}
+
+// The protocol streams are infinite JSON arrays, normally with one element per line. Commas may
+// appear at either end of a line, and the opening bracket may share a line with the first
+// element. An element that is not complete at the end of a line, such as a pretty-printed
+// status line, is continued on the next ones.
+//
+// Elements that fail to parse are reported once, together with the raw line. When an element is
+// valid JSON it is simply skipped. After a syntax error parsing resumes at the next element that
+// starts after the error outside of any string, so that a truncated write only loses the broken
+// part.
+//
+// Outside of the normal parse mode every element is read into a `Value` first, so the mode can
+// check or rewrite it. Elements that did not need to be rewritten are then deserialized from the
+// line itself, so that they can still borrow from it. Elements that span several lines are
+// always owned.
+#[derive(Debug, Clone)]
+pub struct ArrayFraming {
+    state: State,
+    element_start: char,
+    mode: ParseMode,
+    apply_mode: mode::ApplyMode,
+    // The start of an element that continues on the next line.
+    pending: String,
+}
+
+impl ArrayFraming {
+    fn with_mode(
+        element_start: char,
+        mode: ParseMode,
+        apply_mode: mode::ApplyMode,
+    ) -> ArrayFraming {
+        ArrayFraming {
+            state: State::BeforeArray,
+            element_start,
+            mode,
+            apply_mode,
+            pending: String::new(),
+        }
+    }
+
+    pub fn status_lines(mode: ParseMode) -> ArrayFraming {
+        ArrayFraming::with_mode('[', mode, mode::apply_to_status_line)
+    }
+
+    pub fn click_events(mode: ParseMode) -> ArrayFraming {
+        ArrayFraming::with_mode('{', mode, mode::apply_to_click_event)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    pub fn elements<'a, T, F>(&mut self, line: &'a str, mut emit: F)
+    where
+        T: Deserialize<'a>,
+        F: FnMut(Result<T, ParseError>),
+    {
+        if !self.pending.is_empty() {
+            let text = self.continued(line);
+            self.pending.clear();
+            self.parse_elements(&text, &text, ArrayFraming::parse_owned, &mut emit);
+            return;
+        }
+
+        let mut rest = line.trim();
+
+        match self.state {
+            State::Closed => return,
+            State::BeforeArray => {
+                if rest.is_empty() {
+                    return;
+                }
+                if !rest.starts_with('[') {
+                    emit(Err(invalid_line(
+                        line,
+                        ParseError::InvalidData(String::from("Expected start of array")),
+                    )));
+                    return;
+                }
+                rest = &rest[1..];
+                self.state = State::InArray;
+            }
+            State::InArray => {}
+        }
+
+        self.parse_elements(line, rest, ArrayFraming::parse_element, &mut emit);
+    }
+
+    // Reports an element that was still incomplete when the stream ended.
+    pub fn finish(&mut self) -> Option<ParseError> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let text = mem::take(&mut self.pending);
+        Some(invalid_line(
+            &text,
+            ParseError::InvalidData(String::from("Stream ended inside an element")),
+        ))
+    }
+
+    fn continued(&self, line: &str) -> String {
+        let mut text = self.pending.clone();
+        text.push('\n');
+        text.push_str(line.trim());
+        text
+    }
+
+    fn parse_elements<'a, 'b, T, F, P>(
+        &mut self,
+        raw: &str,
+        mut rest: &'b str,
+        parse: P,
+        emit: &mut F,
+    ) where
+        T: Deserialize<'a>,
+        F: FnMut(Result<T, ParseError>),
+        P: Fn(&ArrayFraming, &'b str) -> Result<T, ParseError>,
+    {
+        loop {
+            rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+
+            if rest.is_empty() {
+                return;
+            }
+            if rest.starts_with(']') {
+                self.state = State::Closed;
+                return;
+            }
+
+            match element_length(rest) {
+                Ok(length) => {
+                    emit(parse(self, &rest[..length]).map_err(|error| invalid_line(raw, error)));
+                    rest = &rest[length..];
+                }
+                Err(ref error) if error.is_eof() && rest.len() <= MAX_LINE_LENGTH => {
+                    self.pending = rest.to_owned();
+                    return;
+                }
+                Err(error) => {
+                    let resume = error_offset(rest, &error);
+                    emit(Err(invalid_line(raw, error.into())));
+                    match self.resynchronize(rest, resume, &parse) {
+                        Some(offset) => rest = &rest[offset..],
+                        None => return,
+                    }
+                }
+            }
+        }
+    }
+
+    // True when the line only holds separators, brackets or the start of an element that is
+    // continued on the next line, and so would not produce any element.
+    pub fn is_padding(&self, line: &str) -> bool {
+        if !self.pending.is_empty() {
+            return is_incomplete(&self.continued(line));
+        }
+
+        let mut rest = line.trim();
+        match self.state {
+            State::Closed => return true,
+            State::BeforeArray => match rest.strip_prefix('[') {
+                Some(inner) => rest = inner,
+                None => return rest.is_empty(),
+            },
+            State::InArray => {}
+        }
+
+        let rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        rest.is_empty() || rest.starts_with(']') || is_incomplete(rest)
+    }
+
+    // Finds the first element that starts at or after `from` and parses, never starting inside
+    // a string. JSON strings cannot span lines, so a line break always ends a truncated one.
+    fn resynchronize<'a, 'b, T, P>(&self, text: &'b str, from: usize, parse: &P) -> Option<usize>
+    where
+        T: Deserialize<'a>,
+        P: Fn(&ArrayFraming, &'b str) -> Result<T, ParseError>,
+    {
+        let mut in_string = false;
+        let mut escaped = false;
+        for (offset, c) in text.char_indices() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' | '\n' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            if c == '"' {
+                in_string = true;
+            } else if c == self.element_start && offset > 0 && offset >= from {
+                let candidate = &text[offset..];
+                if let Ok(length) = element_length(candidate) {
+                    if parse(self, &candidate[..length]).is_ok() {
+                        return Some(offset);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn parse_element<'a, T: Deserialize<'a>>(&self, text: &'a str) -> Result<T, ParseError> {
+        if self.mode == ParseMode::Normal {
+            return serde_json::from_str(text).map_err(Into::into);
+        }
+
+        let mut value: Value = serde_json::from_str(text)?;
+        if (self.apply_mode)(&mut value, self.mode)? {
+            T::deserialize(value).map_err(Into::into)
+        } else {
+            serde_json::from_str(text).map_err(Into::into)
+        }
+    }
+
+    fn parse_owned<'a, T: Deserialize<'a>>(&self, text: &str) -> Result<T, ParseError> {
+        let mut value: Value = serde_json::from_str(text)?;
+        if self.mode != ParseMode::Normal {
+            (self.apply_mode)(&mut value, self.mode)?;
+        }
+        T::deserialize(value).map_err(Into::into)
+    }
+}
+
+// The length of the JSON value at the start of text.
+fn element_length(text: &str) -> Result<usize, serde_json::Error> {
+    let mut values = serde_json::Deserializer::from_str(text).into_iter::<IgnoredAny>();
+    match values.next() {
+        Some(Ok(_)) => Ok(values.byte_offset()),
+        Some(Err(error)) => Err(error),
+        None => unreachable!("text is never empty"),
+    }
+}
+
+fn is_incomplete(text: &str) -> bool {
+    match element_length(text) {
+        Err(ref error) => error.is_eof() && text.len() <= MAX_LINE_LENGTH,
+        Ok(_) => false,
+    }
+}
+
+// Where in text a syntax error was found.
+fn error_offset(text: &str, error: &serde_json::Error) -> usize {
+    let line_start: usize = text
+        .split_inclusive('\n')
+        .take(error.line().saturating_sub(1))
+        .map(str::len)
+        .sum();
+    (line_start + error.column().saturating_sub(1)).min(text.len())
+}
+
+fn invalid_line(line: &str, error: ParseError) -> ParseError {
+    ParseError::InvalidLine(
+        line.trim_end_matches(['\n', '\r']).to_owned(),
+        Box::new(error),
+    )
+}
+
+// Longer lines are reported as errors and skipped, so that a stream without newlines cannot make
+// the buffer grow without bounds.
+const MAX_LINE_LENGTH: usize = 1024 * 1024;
+
+// Splits arbitrary chunks of bytes into lines for the non-blocking readers. Lines that have been
+// returned stay in the buffer until the next chunk arrives, so that a chunk holding many lines is
+// only moved once.
+#[derive(Debug, Clone)]
+pub struct LineBuffer {
+    buffer: Vec<u8>,
+    // Where the first line that has not been returned yet starts.
+    start: usize,
+    scanned: usize,
+    max_length: usize,
+    // Set while the rest of a line that was too long is being dropped.
+    skipping: bool,
+}
+
+impl LineBuffer {
+    pub fn new() -> LineBuffer {
+        LineBuffer::with_max_length(MAX_LINE_LENGTH)
+    }
+
+    fn with_max_length(max_length: usize) -> LineBuffer {
+        LineBuffer {
+            buffer: Vec::new(),
+            start: 0,
+            scanned: 0,
+            max_length,
+            skipping: false,
+        }
+    }
+
+    pub fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.drain(..self.start);
+        self.scanned -= self.start;
+        self.start = 0;
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    pub fn next_line(&mut self) -> Option<Result<String, ParseError>> {
+        loop {
+            let start = self.start;
+            match self.buffer[self.scanned..].iter().position(|&b| b == b'\n') {
+                Some(position) => {
+                    let end = self.scanned + position + 1;
+                    self.start = end;
+                    self.scanned = end;
+
+                    if mem::replace(&mut self.skipping, false) {
+                        continue;
+                    }
+                    if end - start > self.max_length {
+                        return Some(Err(self.too_long()));
+                    }
+                    return Some(Ok(
+                        String::from_utf8_lossy(&self.buffer[start..end]).into_owned()
+                    ));
+                }
+                None => {
+                    self.scanned = self.buffer.len();
+                    if self.skipping {
+                        self.start = self.scanned;
+                    } else if self.scanned - start > self.max_length {
+                        self.start = self.scanned;
+                        self.skipping = true;
+                        return Some(Err(self.too_long()));
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+
+    fn too_long(&self) -> ParseError {
+        ParseError::InvalidData(format!("Line is longer than {} bytes", self.max_length))
+    }
+
+    pub fn take_remaining(&mut self) -> Option<String> {
+        let remaining = &self.buffer[self.start..];
+        let line = if self.skipping || remaining.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(remaining).into_owned())
+        };
+
+        self.buffer.clear();
+        self.start = 0;
+        self.scanned = 0;
+        self.skipping = false;
+        line
+    }
+}
+
+// Status lines hold either owned blocks or, for the borrowing reader, `BlockRef`s.
+#[derive(Debug)]
+pub enum Frame<B = Block> {
+    Header(Header),
+    StatusLine(Vec<B>),
+}
+
+#[derive(Debug, Clone)]
+enum Mode {
+    // Holds the start of a header that continues on the next line.
+    AwaitingHeader(String),
+    Json(ArrayFraming),
+    PlainText,
+}
+
+// i3bar falls back to plain text, one status line per input line, when the first line is not a
+// valid JSON header. That mode still produces a (default) header so readers can treat both
+// kinds of streams the same way. A first line that starts an incomplete JSON object is joined
+// with the following ones before deciding, so that pretty-printed headers are recognized.
+//
+// A header that only fails the strict parse mode is reported as an error rather than being
+// taken for plain text, and the status lines after it are still read as JSON.
+#[derive(Debug, Clone)]
+pub struct StatusLineFraming {
+    mode: Mode,
+    parse_mode: ParseMode,
+}
+
+impl StatusLineFraming {
+    pub fn with_mode(parse_mode: ParseMode) -> StatusLineFraming {
+        StatusLineFraming {
+            mode: Mode::AwaitingHeader(String::new()),
+            parse_mode,
+        }
+    }
+
+    pub fn is_plain_text(&self) -> bool {
+        matches!(self.mode, Mode::PlainText)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self.mode {
+            Mode::Json(ref framing) => framing.is_closed(),
+            _ => false,
+        }
+    }
+
+    pub fn is_padding(&self, line: &str) -> bool {
+        match self.mode {
+            Mode::AwaitingHeader(_) => line.trim().is_empty(),
+            Mode::Json(ref framing) => framing.is_padding(line),
+            Mode::PlainText => false,
+        }
+    }
+
+    pub fn finish(&mut self) -> Option<ParseError> {
+        match self.mode {
+            Mode::Json(ref mut framing) => framing.finish(),
+            _ => None,
+        }
+    }
+
+    pub fn push_line<'a, B>(
+        &mut self,
+        line: &'a str,
+        frames: &mut VecDeque<Result<Frame<B>, ParseError>>,
+    ) where
+        B: Deserialize<'a> + From<Block>,
+    {
+        match self.mode {
+            Mode::AwaitingHeader(ref mut pending) => {
+                pending.push_str(line);
+                let trimmed = pending.trim();
+                if trimmed.is_empty() {
+                    pending.clear();
+                    return;
+                }
+                if trimmed.starts_with('{') && is_incomplete(trimmed) {
+                    return;
+                }
+
+                let lines = mem::take(pending);
+                self.read_header(&lines, frames);
+            }
+            Mode::Json(ref mut framing) => framing.elements(line, |result| {
+                frames.push_back(result.map(Frame::StatusLine))
+            }),
+            Mode::PlainText => frames.push_back(Ok(plain_text_line(line))),
+        }
+    }
+
+    fn read_header<B: From<Block>>(
+        &mut self,
+        lines: &str,
+        frames: &mut VecDeque<Result<Frame<B>, ParseError>>,
+    ) {
+        let trimmed = lines.trim();
+        match Header::from_str_mode(trimmed, self.parse_mode) {
+            Ok(header) => {
+                self.mode = Mode::Json(ArrayFraming::status_lines(self.parse_mode));
+                frames.push_back(Ok(Frame::Header(header)));
+            }
+            Err(error) if trimmed.parse::<Header>().is_ok() => {
+                self.mode = Mode::Json(ArrayFraming::status_lines(self.parse_mode));
+                frames.push_back(Err(invalid_line(lines, error)));
+            }
+            Err(_) => {
+                self.mode = Mode::PlainText;
+                frames.push_back(Ok(Frame::Header(Header::default())));
+                for line in lines.split_inclusive('\n') {
+                    frames.push_back(Ok(plain_text_line(line)));
+                }
+            }
+        }
+    }
+}
+
+fn plain_text_line<B: From<Block>>(line: &str) -> Frame<B> {
+    let text = line.trim_end_matches(['\n', '\r']);
+    Frame::StatusLine(vec![BlockBuilder::new(text).build().into()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(framing: &mut ArrayFraming, line: &str) -> Vec<Result<Vec<u32>, ParseError>> {
+        let mut results = Vec::new();
+        framing.elements(line, |result| results.push(result));
+        results
+    }
+
+    fn values(framing: &mut ArrayFraming, line: &str) -> Vec<Vec<u32>> {
+        elements(framing, line)
+            .into_iter()
+            .map(|result| result.expect("Could not parse element"))
+            .collect()
+    }
+
+    #[test]
+    fn it_strips_commas_and_brackets() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+
+        assert!(values(&mut framing, "\n").is_empty());
+        assert!(values(&mut framing, "[\n").is_empty());
+        assert_eq!(values(&mut framing, "[1],\n"), vec![vec![1]]);
+        assert_eq!(values(&mut framing, ",[2]\n"), vec![vec![2]]);
+        assert_eq!(
+            values(&mut framing, "  ,  [] , \n"),
+            vec![Vec::<u32>::new()]
+        );
+        assert!(!framing.is_closed());
+
+        assert!(values(&mut framing, "]\n").is_empty());
+        assert!(framing.is_closed());
+        assert!(values(&mut framing, "[3]\n").is_empty());
+    }
+
+    #[test]
+    fn it_allows_first_element_on_opening_line() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+        assert_eq!(values(&mut framing, "[[1],\n"), vec![vec![1]]);
+    }
+
+    #[test]
+    fn it_reads_several_elements_per_line() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+        assert_eq!(
+            values(&mut framing, "[[1],[2, 3]\n"),
+            vec![vec![1], vec![2, 3]]
+        );
+    }
+
+    #[test]
+    fn it_rejects_missing_opening_bracket() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+        let results = elements(&mut framing, "{}\n");
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap_err().raw_line(), Some("{}"));
+        assert_eq!(values(&mut framing, "[[1]\n"), vec![vec![1]]);
+    }
+
+    #[test]
+    fn it_resynchronizes_after_bad_elements() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+        assert!(values(&mut framing, "[\n").is_empty());
+
+        let results = elements(&mut framing, ",[1, x,[3]\n");
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].as_ref().unwrap_err().raw_line(),
+            Some(",[1, x,[3]")
+        );
+        assert_eq!(results[1].as_ref().unwrap(), &vec![3]);
+
+        let results = elements(&mut framing, "debug output\n");
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+
+        assert_eq!(values(&mut framing, ",[4]\n"), vec![vec![4]]);
+
+        let results = elements(&mut framing, ",[\"[5]\" x \"[6]\" [7]]\n");
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_err());
+        assert_eq!(results[1].as_ref().unwrap(), &vec![7]);
+    }
+
+    #[test]
+    fn it_continues_elements_on_the_next_lines() {
+        let mut framing = ArrayFraming::status_lines(ParseMode::Normal);
+
+        for line in &["[\n", "[\n", "1,\n"] {
+            assert!(framing.is_padding(line));
+            assert!(values(&mut framing, line).is_empty());
+        }
+        assert!(!framing.is_padding("2\n],\n"));
+        assert_eq!(
+            values(&mut framing, "2\n],[3]\n"),
+            vec![vec![1, 2], vec![3]]
+        );
+        assert!(!framing.is_closed());
+
+        assert!(values(&mut framing, ",[4,\n").is_empty());
+        let error = framing
+            .finish()
+            .expect("Incomplete element was not reported");
+        assert_eq!(error.raw_line(), Some("[4,"));
+        assert!(framing.finish().is_none());
+    }
+
+    fn next_line(lines: &mut LineBuffer) -> Option<String> {
+        lines
+            .next_line()
+            .map(|line| line.expect("Could not read line"))
+    }
+
+    #[test]
+    fn it_splits_chunks_into_lines() {
+        let mut lines = LineBuffer::new();
+
+        lines.extend(b"[{\"full");
+        assert_eq!(next_line(&mut lines), None);
+        lines.extend(b"_text\":\"a\"}]\n,[]\n,[");
+        assert_eq!(
+            next_line(&mut lines).as_deref(),
+            Some("[{\"full_text\":\"a\"}]\n")
+        );
+        assert_eq!(next_line(&mut lines).as_deref(), Some(",[]\n"));
+        assert_eq!(next_line(&mut lines), None);
+        assert_eq!(lines.take_remaining().as_deref(), Some(",["));
+        assert_eq!(lines.take_remaining(), None);
+    }
+
+    #[test]
+    fn it_skips_lines_that_are_too_long() {
+        let mut lines = LineBuffer::with_max_length(8);
+
+        lines.extend(b"short\n0123456789");
+        assert_eq!(next_line(&mut lines).as_deref(), Some("short\n"));
+        assert!(lines.next_line().unwrap().is_err());
+        assert!(lines.next_line().is_none());
+
+        lines.extend(b"more garbage");
+        assert!(lines.next_line().is_none());
+        lines.extend(b" and more\nok\n0123456789\n");
+        assert_eq!(next_line(&mut lines).as_deref(), Some("ok\n"));
+        assert!(lines.next_line().unwrap().is_err());
+        assert!(lines.next_line().is_none());
+
+        lines.extend(b"012345678901");
+        assert!(lines.next_line().unwrap().is_err());
+        assert_eq!(lines.take_remaining(), None);
+    }
+
+    fn push(framing: &mut StatusLineFraming, line: &str) -> Vec<Result<Frame, ParseError>> {
+        let mut frames = VecDeque::new();
+        framing.push_line(line, &mut frames);
+        frames.into_iter().collect()
+    }
+
+    #[test]
+    fn it_frames_json_status_lines() {
+        let mut framing = StatusLineFraming::with_mode(ParseMode::Normal);
+
+        match push(&mut framing, "{\"version\":1}\n").as_slice() {
+            [Ok(Frame::Header(header))] => assert_eq!(header.version(), 1),
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+        assert!(push(&mut framing, "[\n").is_empty());
+        match push(&mut framing, "[{\"full_text\":\"a\"}],\n").as_slice() {
+            [Ok(Frame::StatusLine(blocks))] => assert_eq!(blocks[0].full_text(), "a"),
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+        assert!(!framing.is_plain_text());
+    }
+
+    #[test]
+    fn it_joins_headers_split_across_lines() {
+        let mut framing = StatusLineFraming::with_mode(ParseMode::Normal);
+
+        assert!(push(&mut framing, "\n").is_empty());
+        assert!(push(&mut framing, "{\n").is_empty());
+        assert!(push(&mut framing, "  \"version\":1,\n").is_empty());
+        match push(&mut framing, "  \"click_events\":true\n}\n").as_slice() {
+            [Ok(Frame::Header(header))] => assert!(header.click_events()),
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+        assert!(!framing.is_plain_text());
+
+        let mut framing = StatusLineFraming::with_mode(ParseMode::Normal);
+        assert!(push(&mut framing, "{\n").is_empty());
+        match push(&mut framing, "CPU: 5%\n").as_slice() {
+            [Ok(Frame::Header(_)), Ok(Frame::StatusLine(first)), Ok(Frame::StatusLine(second))] => {
+                assert_eq!(first[0].full_text(), "{");
+                assert_eq!(second[0].full_text(), "CPU: 5%");
+            }
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+        assert!(framing.is_plain_text());
+    }
+
+    #[test]
+    fn it_falls_back_to_plain_text() {
+        let mut framing = StatusLineFraming::with_mode(ParseMode::Normal);
+
+        match push(&mut framing, "Battery: 50%\n").as_slice() {
+            [Ok(Frame::Header(header)), Ok(Frame::StatusLine(blocks))] => {
+                assert_eq!(header, &Header::default());
+                assert_eq!(blocks.len(), 1);
+                assert_eq!(blocks[0].full_text(), "Battery: 50%");
+            }
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+        assert!(framing.is_plain_text());
+
+        match push(&mut framing, "[not, json]\r\n").as_slice() {
+            [Ok(Frame::StatusLine(blocks))] => assert_eq!(blocks[0].full_text(), "[not, json]"),
+            other => panic!("Unexpected frames: {:?}", other),
+        }
+    }
+}
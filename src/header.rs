@@ -3,9 +3,10 @@ extern crate serde_json;
 
 use std::fmt;
 use std::str::FromStr;
-use super::ParseError;
+use super::{ParseError, ParseMode};
+use mode;
 
-#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
 pub struct Header {
     version: u8,
 
@@ -34,7 +35,13 @@ impl FromStr for Header {
     type Err = ParseError;
 
     fn from_str(str: &str) -> Result<Header, ParseError> {
-        let mut header: Header = serde_json::from_str(str)?;
+        Header::from_str_mode(str, ParseMode::Normal)
+    }
+}
+
+impl Header {
+    pub fn from_str_mode(str: &str, mode: ParseMode) -> Result<Header, ParseError> {
+        let mut header: Header = mode::parse(str, &mode::HEADER, mode)?;
 
         if header.stop_signal == 0 {
             header.stop_signal = libc::SIGSTOP;
@@ -46,6 +53,22 @@ impl FromStr for Header {
 
         Ok(header)
     }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn stop_signal(&self) -> i32 {
+        self.stop_signal
+    }
+
+    pub fn continue_signal(&self) -> i32 {
+        self.continue_signal
+    }
+
+    pub fn click_events(&self) -> bool {
+        self.click_events
+    }
 }
 
 impl fmt::Display for Header {
@@ -56,7 +79,7 @@ impl fmt::Display for Header {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Default)]
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
 pub struct HeaderBuilder {
     stop_signal: i32,
     continue_signal: i32,
@@ -110,7 +133,7 @@ mod tests {
             .build();
 
         assert_eq!(header.version, 1);
-        assert_eq!(header.click_events, false);
+        assert!(!header.click_events);
         assert_eq!(header.continue_signal, 5);
         assert_eq!(header.stop_signal, libc::SIGKILL);
     }
@@ -121,7 +144,7 @@ mod tests {
         let header: Header = json.parse().expect("Could not parse Header");
 
         assert_eq!(header.version, 1);
-        assert_eq!(header.click_events, true);
+        assert!(header.click_events);
         assert_eq!(header.continue_signal, 12);
         assert_eq!(header.stop_signal, 10);
 
@@ -134,7 +157,7 @@ mod tests {
         let header: Header = json.parse().expect("Could not parse Header");
 
         assert_eq!(header.version, 1);
-        assert_eq!(header.click_events, false);
+        assert!(!header.click_events);
         assert_eq!(header.continue_signal, libc::SIGCONT);
         assert_eq!(header.stop_signal, libc::SIGSTOP);
     }
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::io;
 use std::error::Error;
 
 extern crate serde;
@@ -8,6 +9,16 @@ extern crate serde_derive;
 
 extern crate serde_json;
 
+#[cfg(feature = "tokio")]
+extern crate futures_core;
+#[cfg(feature = "tokio")]
+extern crate futures_sink;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+
+#[cfg(test)]
+extern crate futures;
+
 //
 
 mod enums;
@@ -19,14 +30,75 @@ pub use click_event::{ClickEvent, ClickEventBuilder};
 mod header;
 pub use header::{Header, HeaderBuilder};
 
+mod color;
+pub use color::Color;
+
 mod block;
-pub use block::Block;
+pub use block::{Block, BlockBuilder, BlockRef, BorderWidths, MinWidth};
+
+mod status_line;
+pub use status_line::StatusLine;
+
+mod diff;
+pub use diff::{field_changes, BlockChanges, Field, FieldChange, Move, StatusLineDiff};
+
+mod mode;
+pub use mode::ParseMode;
+
+mod pango;
+pub use pango::{FontSize, FontStyle, FontWeight, PangoMarkup, Span, Underline};
+
+mod pango_parser;
+pub use pango_parser::{
+    parse_markup, Attributes, MarkupError, MarkupErrorKind, SpanColor, TextRun,
+};
+
+mod validation;
+pub use validation::{Problem, Severity, Warning};
+
+mod framing;
+
+mod decoder;
+pub use decoder::{Decoded, Decoder};
+
+mod reader;
+pub use reader::{ClickEventReader, StatusLineReader};
+
+mod writer;
+pub use writer::{ClickEventWriter, StatusLineWriter};
+
+mod session;
+pub use session::{BarSession, StatusCommandSession};
+
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "tokio")]
+pub use async_io::{ClickEventSink, ClickEventStream, StatusLineSink, StatusLineStream};
 
 #[derive(Debug, Clone)]
 pub enum ParseError {
     ReadError(String),
     JsonError(String),
     InvalidData(String),
+    InvalidLine(String, Box<ParseError>),
+}
+
+impl ParseError {
+    pub fn raw_line(&self) -> Option<&str> {
+        match *self {
+            ParseError::InvalidLine(ref line, _) => Some(line),
+            _ => None,
+        }
+    }
+
+    fn message(&self) -> &str {
+        match *self {
+            ParseError::ReadError(ref s) |
+            ParseError::JsonError(ref s) |
+            ParseError::InvalidData(ref s) => s,
+            ParseError::InvalidLine(_, ref error) => error.message(),
+        }
+    }
 }
 
 impl fmt::Display for ParseError {
@@ -37,10 +109,13 @@ impl fmt::Display for ParseError {
 
 impl Error for ParseError {
     fn description(&self) -> &str {
+        self.message()
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match *self {
-            ParseError::ReadError(ref s) |
-            ParseError::JsonError(ref s) |
-            ParseError::InvalidData(ref s) => s,
+            ParseError::InvalidLine(_, ref error) => Some(error.as_ref()),
+            _ => None,
         }
     }
 }
@@ -50,9 +125,15 @@ impl From<serde_json::Error> for ParseError {
         use serde_json::error::Category;
 
         match input.classify() {
-            Category::Io | Category::Eof => ParseError::ReadError(input.description().to_owned()),
+            Category::Io | Category::Eof => ParseError::ReadError(input.to_string()),
             Category::Syntax => ParseError::JsonError(input.to_string()),
             Category::Data => ParseError::InvalidData(input.to_string()),
         }
     }
 }
+
+impl From<io::Error> for ParseError {
+    fn from(input: io::Error) -> ParseError {
+        ParseError::ReadError(input.to_string())
+    }
+}
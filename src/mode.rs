@@ -0,0 +1,257 @@
+extern crate serde_json;
+
+use serde::Deserialize;
+use serde_json::Value;
+use super::{Alignment, Color, Markup, ParseError};
+
+// Normal parsing ignores unknown keys and rejects unknown enum values, which is what the derived
+// implementations do. Strict parsing also rejects unknown keys other than the underscore-prefixed
+// ones i3bar reserves for status programs. Lenient parsing follows i3bar, which falls back to
+// the defaults for unknown `align` and `markup` values, ignores invalid colors and reads numbers
+// given as strings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    #[default]
+    Normal,
+    Strict,
+    Lenient,
+}
+
+type IsValid = fn(&Value) -> bool;
+
+// Checks or rewrites a parsed element before it is deserialized, returning whether it was
+// rewritten.
+pub type ApplyMode = fn(&mut Value, ParseMode) -> Result<bool, ParseError>;
+
+pub struct Schema {
+    known: &'static [&'static str],
+    numeric: &'static [&'static str],
+    // Values that are dropped when lenient parsing finds them invalid.
+    fallbacks: &'static [(&'static str, IsValid)],
+}
+
+pub const BLOCK: Schema = Schema {
+    known: &[
+        "full_text",
+        "short_text",
+        "color",
+        "background",
+        "border",
+        "border_top",
+        "border_right",
+        "border_bottom",
+        "border_left",
+        "min_width",
+        "align",
+        "urgent",
+        "name",
+        "instance",
+        "separator",
+        "separator_block_width",
+        "markup",
+    ],
+    numeric: &[
+        "separator_block_width",
+        "border_top",
+        "border_right",
+        "border_bottom",
+        "border_left",
+    ],
+    fallbacks: &[
+        ("align", is_alignment),
+        ("markup", is_markup),
+        ("color", is_color),
+        ("background", is_color),
+        ("border", is_color),
+    ],
+};
+
+pub const HEADER: Schema = Schema {
+    known: &["version", "stop_signal", "cont_signal", "click_events"],
+    numeric: &["version", "stop_signal", "cont_signal"],
+    fallbacks: &[],
+};
+
+// Includes the fields i3bar sends that ClickEvent does not keep.
+pub const CLICK_EVENT: Schema = Schema {
+    known: &[
+        "name",
+        "instance",
+        "button",
+        "x",
+        "y",
+        "relative_x",
+        "relative_y",
+        "output_x",
+        "output_y",
+        "width",
+        "height",
+        "modifiers",
+        "scale",
+    ],
+    numeric: &["button", "x", "y"],
+    fallbacks: &[],
+};
+
+fn is_alignment(value: &Value) -> bool {
+    Alignment::deserialize(value).is_ok()
+}
+
+fn is_markup(value: &Value) -> bool {
+    Markup::deserialize(value).is_ok()
+}
+
+fn is_color(value: &Value) -> bool {
+    Option::<Color>::deserialize(value).is_ok()
+}
+
+impl Schema {
+    pub fn apply(&self, value: &mut Value, mode: ParseMode) -> Result<bool, ParseError> {
+        let map = match *value {
+            Value::Object(ref mut map) => map,
+            _ => return Ok(false),
+        };
+        let mut rewritten = false;
+
+        match mode {
+            ParseMode::Normal => {}
+            ParseMode::Strict => {
+                if let Some(key) = map
+                    .keys()
+                    .find(|key| !key.starts_with('_') && !self.known.contains(&key.as_str()))
+                {
+                    return Err(ParseError::InvalidData(format!("Unknown field {:?}", key)));
+                }
+            }
+            ParseMode::Lenient => {
+                for key in self.numeric {
+                    let number = match map.get(*key) {
+                        Some(Value::String(text)) => parse_number(text),
+                        _ => None,
+                    };
+                    if let Some(number) = number {
+                        map.insert((*key).to_owned(), number);
+                        rewritten = true;
+                    }
+                }
+
+                for &(key, is_valid) in self.fallbacks {
+                    if map.get(key).is_some_and(|value| !is_valid(value)) {
+                        map.remove(key);
+                        rewritten = true;
+                    }
+                }
+            }
+        }
+
+        Ok(rewritten)
+    }
+}
+
+fn parse_number(text: &str) -> Option<Value> {
+    let text = text.trim();
+    text.parse::<u64>()
+        .map(Value::from)
+        .or_else(|_| text.parse::<i64>().map(Value::from))
+        .ok()
+}
+
+pub fn parse<'a, T: Deserialize<'a>>(
+    json: &'a str,
+    schema: &Schema,
+    mode: ParseMode,
+) -> Result<T, ParseError> {
+    if mode == ParseMode::Normal {
+        return serde_json::from_str(json).map_err(Into::into);
+    }
+
+    let mut value = serde_json::from_str(json)?;
+    schema.apply(&mut value, mode)?;
+    T::deserialize(value).map_err(Into::into)
+}
+
+// Status lines are arrays of blocks.
+pub fn apply_to_status_line(value: &mut Value, mode: ParseMode) -> Result<bool, ParseError> {
+    let mut rewritten = false;
+    if let Value::Array(ref mut blocks) = *value {
+        for block in blocks {
+            rewritten |= BLOCK.apply(block, mode)?;
+        }
+    }
+    Ok(rewritten)
+}
+
+pub fn apply_to_click_event(value: &mut Value, mode: ParseMode) -> Result<bool, ParseError> {
+    CLICK_EVENT.apply(value, mode)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Block, ClickEvent, Header, MouseButton};
+
+    #[test]
+    fn it_rejects_unknown_keys_when_strict() {
+        let json = r##"{"full_text":"a","_mine":1,"colour":"#ff0000"}"##;
+
+        assert!(Block::from_str_mode(json, ParseMode::Normal).is_ok());
+        assert!(Block::from_str_mode(json, ParseMode::Lenient).is_ok());
+        match Block::from_str_mode(json, ParseMode::Strict) {
+            Err(ParseError::InvalidData(message)) => assert!(message.contains("colour")),
+            other => panic!("Expected unknown field error, got {:?}", other),
+        }
+
+        let block = Block::from_str_mode(r#"{"full_text":"a","_mine":1}"#, ParseMode::Strict)
+            .expect("Could not parse");
+        assert_eq!(block.extra("_mine"), Some(&Value::from(1)));
+
+        assert!(Header::from_str_mode(r#"{"version":1,"extra":true}"#, ParseMode::Strict).is_err());
+        assert!(ClickEvent::from_str_mode(
+            r#"{"name":"a","button":1,"relative_x":3,"modifiers":[]}"#,
+            ParseMode::Strict
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn it_rejects_wrong_types_and_values_when_strict() {
+        for json in &[
+            r#"{"full_text":"a","urgent":"yes"}"#,
+            r#"{"full_text":"a","align":"middle"}"#,
+            r#"{"full_text":"a","separator_block_width":"9"}"#,
+        ] {
+            assert!(Block::from_str_mode(json, ParseMode::Strict).is_err());
+        }
+    }
+
+    #[test]
+    fn it_falls_back_when_lenient() {
+        let json = r#"{"full_text":"a","align":"middle","markup":"html","separator_block_width":"9","border_top":" 2 "}"#;
+
+        assert!(Block::from_str_mode(json, ParseMode::Normal).is_err());
+        let block = Block::from_str_mode(json, ParseMode::Lenient).expect("Could not parse");
+        assert_eq!(block.alignment(), Alignment::Left);
+        assert_eq!(block.markup(), Markup::None);
+        assert_eq!(block.separator_block_width(), Some(9));
+        assert_eq!(block.border_top(), Some(2));
+
+        let json = r##"{"full_text":"a","color":"red","background":7,"border":"#00ff00"}"##;
+        assert!(Block::from_str_mode(json, ParseMode::Normal).is_err());
+        let block = Block::from_str_mode(json, ParseMode::Lenient).expect("Could not parse");
+        assert_eq!(block.color(), None);
+        assert_eq!(block.background(), None);
+        assert_eq!(block.border(), Some(Color::rgb(0, 0xff, 0)));
+
+        let header =
+            Header::from_str_mode(r#"{"version":"1","stop_signal":"10"}"#, ParseMode::Lenient)
+                .expect("Could not parse header");
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.stop_signal(), 10);
+
+        let event =
+            ClickEvent::from_str_mode(r#"{"name":"a","button":"3","x":"12"}"#, ParseMode::Lenient)
+                .expect("Could not parse click event");
+        assert_eq!(event.button(), MouseButton::Right);
+        assert_eq!(event.x(), Some(12));
+    }
+}
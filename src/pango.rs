@@ -0,0 +1,308 @@
+use std::fmt;
+use super::Color;
+
+// Well-formed Pango markup. Text is always escaped on the way in, so the markup can be built up
+// from untrusted strings such as window titles.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PangoMarkup {
+    markup: String,
+}
+
+impl PangoMarkup {
+    pub fn new() -> PangoMarkup {
+        PangoMarkup::default()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.markup
+    }
+
+    pub fn into_string(self) -> String {
+        self.markup
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.markup.is_empty()
+    }
+
+    pub fn text<S: AsRef<str>>(mut self, text: S) -> Self {
+        escape_into(&mut self.markup, text.as_ref());
+        self
+    }
+
+    pub fn push<M: Into<PangoMarkup>>(mut self, content: M) -> Self {
+        self.markup.push_str(&content.into().markup);
+        self
+    }
+
+    pub fn span(self, span: Span) -> Self {
+        self.push(span)
+    }
+
+    pub fn bold<M: Into<PangoMarkup>>(self, content: M) -> Self {
+        self.tag("b", content.into())
+    }
+
+    pub fn italic<M: Into<PangoMarkup>>(self, content: M) -> Self {
+        self.tag("i", content.into())
+    }
+
+    pub fn small<M: Into<PangoMarkup>>(self, content: M) -> Self {
+        self.tag("small", content.into())
+    }
+
+    fn tag(mut self, name: &str, content: PangoMarkup) -> Self {
+        self.markup.push('<');
+        self.markup.push_str(name);
+        self.markup.push('>');
+        self.markup.push_str(&content.markup);
+        self.markup.push_str("</");
+        self.markup.push_str(name);
+        self.markup.push('>');
+        self
+    }
+}
+
+impl<'a> From<&'a str> for PangoMarkup {
+    fn from(text: &'a str) -> PangoMarkup {
+        PangoMarkup::new().text(text)
+    }
+}
+
+impl From<String> for PangoMarkup {
+    fn from(text: String) -> PangoMarkup {
+        PangoMarkup::new().text(text)
+    }
+}
+
+impl fmt::Display for PangoMarkup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.markup)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontWeight {
+    Ultralight,
+    Light,
+    Normal,
+    Bold,
+    Ultrabold,
+    Heavy,
+    Numeric(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontStyle {
+    Normal,
+    Oblique,
+    Italic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontSize {
+    // Pango units, 1024ths of a point.
+    Units(u32),
+    Points(f64),
+    Percent(f64),
+    XXSmall,
+    XSmall,
+    Small,
+    Medium,
+    Large,
+    XLarge,
+    XXLarge,
+    Smaller,
+    Larger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Underline {
+    None,
+    Single,
+    Double,
+    Low,
+    Error,
+}
+
+impl fmt::Display for FontWeight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontWeight::Ultralight => f.write_str("ultralight"),
+            FontWeight::Light => f.write_str("light"),
+            FontWeight::Normal => f.write_str("normal"),
+            FontWeight::Bold => f.write_str("bold"),
+            FontWeight::Ultrabold => f.write_str("ultrabold"),
+            FontWeight::Heavy => f.write_str("heavy"),
+            FontWeight::Numeric(weight) => write!(f, "{}", weight),
+        }
+    }
+}
+
+impl fmt::Display for FontStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            FontStyle::Normal => "normal",
+            FontStyle::Oblique => "oblique",
+            FontStyle::Italic => "italic",
+        })
+    }
+}
+
+impl fmt::Display for FontSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FontSize::Units(units) => write!(f, "{}", units),
+            FontSize::Points(points) => write!(f, "{}pt", points),
+            FontSize::Percent(percent) => write!(f, "{}%", percent),
+            FontSize::XXSmall => f.write_str("xx-small"),
+            FontSize::XSmall => f.write_str("x-small"),
+            FontSize::Small => f.write_str("small"),
+            FontSize::Medium => f.write_str("medium"),
+            FontSize::Large => f.write_str("large"),
+            FontSize::XLarge => f.write_str("x-large"),
+            FontSize::XXLarge => f.write_str("xx-large"),
+            FontSize::Smaller => f.write_str("smaller"),
+            FontSize::Larger => f.write_str("larger"),
+        }
+    }
+}
+
+impl fmt::Display for Underline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Underline::None => "none",
+            Underline::Single => "single",
+            Underline::Double => "double",
+            Underline::Low => "low",
+            Underline::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    attributes: Vec<(&'static str, String)>,
+    content: PangoMarkup,
+}
+
+impl Span {
+    pub fn new<M: Into<PangoMarkup>>(content: M) -> Span {
+        Span {
+            attributes: Vec::new(),
+            content: content.into(),
+        }
+    }
+
+    pub fn foreground(self, color: Color) -> Self {
+        self.attribute("foreground", color.to_hex())
+    }
+
+    pub fn background(self, color: Color) -> Self {
+        self.attribute("background", color.to_hex())
+    }
+
+    pub fn weight(self, weight: FontWeight) -> Self {
+        self.attribute("weight", weight.to_string())
+    }
+
+    pub fn style(self, style: FontStyle) -> Self {
+        self.attribute("style", style.to_string())
+    }
+
+    pub fn size(self, size: FontSize) -> Self {
+        self.attribute("size", size.to_string())
+    }
+
+    pub fn font_family<S: Into<String>>(self, family: S) -> Self {
+        self.attribute("font_family", family.into())
+    }
+
+    pub fn underline(self, underline: Underline) -> Self {
+        self.attribute("underline", underline.to_string())
+    }
+
+    // In Pango units (1024ths of a point); negative values lower the text.
+    pub fn rise(self, rise: i32) -> Self {
+        self.attribute("rise", rise.to_string())
+    }
+
+    // Setting an attribute again replaces the earlier value.
+    fn attribute(mut self, name: &'static str, value: String) -> Self {
+        self.attributes.retain(|&(existing, _)| existing != name);
+        self.attributes.push((name, value));
+        self
+    }
+}
+
+impl From<Span> for PangoMarkup {
+    fn from(span: Span) -> PangoMarkup {
+        let mut markup = String::from("<span");
+        for (name, value) in span.attributes {
+            markup.push(' ');
+            markup.push_str(name);
+            markup.push_str("=\"");
+            escape_into(&mut markup, &value);
+            markup.push('"');
+        }
+        markup.push('>');
+        markup.push_str(&span.content.markup);
+        markup.push_str("</span>");
+
+        PangoMarkup { markup }
+    }
+}
+
+fn escape_into(markup: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => markup.push_str("&amp;"),
+            '<' => markup.push_str("&lt;"),
+            '>' => markup.push_str("&gt;"),
+            '"' => markup.push_str("&quot;"),
+            '\'' => markup.push_str("&apos;"),
+            _ => markup.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse_markup;
+
+    #[test]
+    fn it_escapes_text() {
+        let markup = PangoMarkup::new().text("Tom & Jerry <3 \"quotes\" 'n stuff");
+        assert_eq!(
+            markup.as_str(),
+            "Tom &amp; Jerry &lt;3 &quot;quotes&quot; &apos;n stuff"
+        );
+        assert!(parse_markup(markup.as_str()).is_ok());
+    }
+
+    #[test]
+    fn it_builds_nested_markup() {
+        let markup = PangoMarkup::new().bold("CPU").text(" ").span(
+            Span::new(PangoMarkup::new().italic("5%").small(" & idle"))
+                .foreground(Color::rgb(0xff, 0, 0))
+                .background(Color::rgba(0, 0, 0, 0x80))
+                .weight(FontWeight::Numeric(600))
+                .style(FontStyle::Oblique)
+                .size(FontSize::XLarge)
+                .underline(Underline::Double)
+                .rise(-2048)
+                .font_family("Font \"Awesome\"")
+                .size(FontSize::Points(10.5)),
+        );
+
+        assert_eq!(
+            markup.to_string(),
+            "<b>CPU</b> <span foreground=\"#ff0000\" background=\"#00000080\" weight=\"600\" \
+             style=\"oblique\" underline=\"double\" rise=\"-2048\" \
+             font_family=\"Font &quot;Awesome&quot;\" size=\"10.5pt\">\
+             <i>5%</i><small> &amp; idle</small></span>"
+        );
+        assert!(parse_markup(markup.as_str()).is_ok());
+    }
+}
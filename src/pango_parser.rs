@@ -0,0 +1,798 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use super::{Color, FontSize, FontStyle, FontWeight, Underline};
+
+// Pango accepts X11 color names as well as hex colors. Names are kept as written, since
+// resolving them would need the full X11 color table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpanColor {
+    Rgb(Color),
+    Named(String),
+}
+
+// Attributes in effect for a run of text, after applying every enclosing tag. Inner tags
+// override the values of outer ones.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes {
+    pub foreground: Option<SpanColor>,
+    pub background: Option<SpanColor>,
+    pub weight: Option<FontWeight>,
+    pub style: Option<FontStyle>,
+    pub size: Option<FontSize>,
+    pub font_family: Option<String>,
+    pub font: Option<String>,
+    pub underline: Option<Underline>,
+    pub rise: Option<i32>,
+    pub strikethrough: Option<bool>,
+    // Span attributes that Pango knows but that are not interpreted here, by name.
+    pub other: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    pub text: String,
+    pub attributes: Attributes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupErrorKind {
+    UnknownTag(String),
+    UnknownAttribute(String, String),
+    BadAttributeValue(String, String),
+    BadEntity(String),
+    // The expected closing tag, if any tag was open, and the one that was found.
+    UnbalancedTag(Option<String>, String),
+    UnclosedTag(String),
+    MalformedTag,
+}
+
+// Offsets are in bytes from the start of the markup and point at the tag or entity at fault.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkupError {
+    pub offset: usize,
+    pub kind: MarkupErrorKind,
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            MarkupErrorKind::UnknownTag(ref tag) => write!(f, "unknown tag <{}>", tag),
+            MarkupErrorKind::UnknownAttribute(ref tag, ref attribute) => {
+                write!(f, "unknown attribute {:?} on <{}>", attribute, tag)
+            }
+            MarkupErrorKind::BadAttributeValue(ref attribute, ref value) => {
+                write!(f, "bad value {:?} for attribute {:?}", value, attribute)
+            }
+            MarkupErrorKind::BadEntity(ref entity) => write!(f, "bad entity &{};", entity),
+            MarkupErrorKind::UnbalancedTag(Some(ref expected), ref found) => {
+                write!(f, "expected </{}> but found </{}>", expected, found)
+            }
+            MarkupErrorKind::UnbalancedTag(None, ref found) => {
+                write!(f, "unexpected </{}>", found)
+            }
+            MarkupErrorKind::UnclosedTag(ref tag) => write!(f, "<{}> is never closed", tag),
+            MarkupErrorKind::MalformedTag => write!(f, "malformed tag"),
+        }?;
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+impl Error for MarkupError {}
+
+const OTHER_SPAN_ATTRIBUTES: &[&str] = &[
+    "alpha",
+    "allow_breaks",
+    "baseline_shift",
+    "bgalpha",
+    "fallback",
+    "fgalpha",
+    "font_features",
+    "font_scale",
+    "font_stretch",
+    "font_variant",
+    "gravity",
+    "gravity_hint",
+    "insert_hyphens",
+    "lang",
+    "letter_spacing",
+    "line_height",
+    "overline",
+    "overline_color",
+    "segment",
+    "show",
+    "stretch",
+    "strikethrough_color",
+    "text_transform",
+    "underline_color",
+    "variant",
+];
+
+struct OpenTag {
+    name: String,
+    offset: usize,
+    attributes: Attributes,
+}
+
+pub fn parse_markup(markup: &str) -> Result<Vec<TextRun>, MarkupError> {
+    let mut runs: Vec<TextRun> = Vec::new();
+    let mut open: Vec<OpenTag> = Vec::new();
+    let mut text = String::new();
+    let mut position = 0;
+
+    while let Some(found) = markup[position..].find(['<', '&']) {
+        let offset = position + found;
+        text.push_str(&markup[position..offset]);
+
+        if markup[offset..].starts_with('&') {
+            let (decoded, length) = decode_entity(markup, offset)?;
+            text.push(decoded);
+            position = offset + length;
+            continue;
+        }
+
+        // Like GMarkup, comments and processing instructions are skipped without ending the run.
+        if let Some(end) = skipped_end(markup, offset) {
+            position = end?;
+            continue;
+        }
+
+        let end = tag_end(markup, offset).ok_or(MarkupError {
+            offset,
+            kind: MarkupErrorKind::MalformedTag,
+        })?;
+        let tag = &markup[offset + 1..end];
+        position = end + 1;
+
+        let current = open
+            .last()
+            .map(|tag| tag.attributes.clone())
+            .unwrap_or_default();
+        push_run(&mut runs, &mut text, &current);
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim();
+            match open.pop() {
+                Some(ref expected) if expected.name == name => {}
+                expected => {
+                    return Err(MarkupError {
+                        offset,
+                        kind: MarkupErrorKind::UnbalancedTag(
+                            expected.map(|tag| tag.name),
+                            name.to_owned(),
+                        ),
+                    })
+                }
+            }
+            continue;
+        }
+
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, attributes) = parse_tag(markup, offset, tag, current)?;
+        if !self_closing {
+            open.push(OpenTag {
+                name,
+                offset,
+                attributes,
+            });
+        }
+    }
+
+    text.push_str(&markup[position..]);
+    if let Some(tag) = open.pop() {
+        return Err(MarkupError {
+            offset: tag.offset,
+            kind: MarkupErrorKind::UnclosedTag(tag.name),
+        });
+    }
+    push_run(&mut runs, &mut text, &Attributes::default());
+
+    Ok(runs)
+}
+
+// Adjacent text with the same attributes ends up in a single run.
+fn push_run(runs: &mut Vec<TextRun>, text: &mut String, attributes: &Attributes) {
+    if text.is_empty() {
+        return;
+    }
+
+    match runs.last_mut() {
+        Some(ref mut run) if run.attributes == *attributes => run.text.push_str(text),
+        _ => runs.push(TextRun {
+            text: text.clone(),
+            attributes: attributes.clone(),
+        }),
+    }
+    text.clear();
+}
+
+// Finds the end of the comment or processing instruction starting at offset, if there is one.
+fn skipped_end(markup: &str, offset: usize) -> Option<Result<usize, MarkupError>> {
+    let rest = &markup[offset..];
+    let (start, end) = if rest.starts_with("<!--") {
+        ("<!--", "-->")
+    } else if rest.starts_with("<?") {
+        ("<?", "?>")
+    } else {
+        return None;
+    };
+
+    Some(
+        rest[start.len()..]
+            .find(end)
+            .map(|found| offset + start.len() + found + end.len())
+            .ok_or(MarkupError {
+                offset,
+                kind: MarkupErrorKind::MalformedTag,
+            }),
+    )
+}
+
+// Finds the closing `>` of the tag starting at offset, skipping over quoted attribute values.
+fn tag_end(markup: &str, offset: usize) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in markup[offset + 1..].char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(offset + 1 + index),
+            (None, '<') => return None,
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(open), _) if open == c => quote = None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn decode_entity(markup: &str, offset: usize) -> Result<(char, usize), MarkupError> {
+    let rest = &markup[offset + 1..];
+    let end = rest
+        .find(is_entity_end)
+        .filter(|&end| rest[end..].starts_with(';'));
+    let bad = |entity: &str| MarkupError {
+        offset,
+        kind: MarkupErrorKind::BadEntity(entity.to_owned()),
+    };
+
+    let end = match end {
+        Some(end) => end,
+        None => {
+            let length = rest.find(is_entity_end).unwrap_or(rest.len());
+            return Err(bad(&rest[..length]));
+        }
+    };
+    let entity = &rest[..end];
+
+    let decoded = match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => entity
+            .strip_prefix('#')
+            .and_then(|number| match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse::<u32>().ok(),
+            })
+            .filter(|&code| code != 0)
+            .and_then(::std::char::from_u32),
+    };
+
+    decoded.map(|c| (c, end + 2)).ok_or_else(|| bad(entity))
+}
+
+fn is_entity_end(c: char) -> bool {
+    c == ';' || c == '&' || c == '<' || c == '"' || c == '\'' || c.is_whitespace()
+}
+
+fn parse_tag(
+    markup: &str,
+    offset: usize,
+    tag: &str,
+    mut attributes: Attributes,
+) -> Result<(String, Attributes), MarkupError> {
+    let malformed = || MarkupError {
+        offset,
+        kind: MarkupErrorKind::MalformedTag,
+    };
+
+    let name_end = tag.find(|c: char| c.is_whitespace()).unwrap_or(tag.len());
+    let name = &tag[..name_end];
+    if name.is_empty() {
+        return Err(malformed());
+    }
+
+    match name {
+        "markup" | "span" => {}
+        "b" => attributes.weight = Some(FontWeight::Bold),
+        "i" => attributes.style = Some(FontStyle::Italic),
+        "s" => attributes.strikethrough = Some(true),
+        "u" => attributes.underline = Some(Underline::Single),
+        "tt" => attributes.font_family = Some(String::from("Monospace")),
+        "big" => attributes.size = Some(FontSize::Larger),
+        "small" => attributes.size = Some(FontSize::Smaller),
+        "sub" => {
+            attributes.rise = Some(-5000);
+            attributes.size = Some(FontSize::Smaller);
+        }
+        "sup" => {
+            attributes.rise = Some(5000);
+            attributes.size = Some(FontSize::Smaller);
+        }
+        _ => {
+            return Err(MarkupError {
+                offset,
+                kind: MarkupErrorKind::UnknownTag(name.to_owned()),
+            })
+        }
+    }
+
+    let mut rest = tag[name_end..].trim_start();
+    while !rest.is_empty() {
+        let attribute_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .ok_or_else(malformed)?;
+        let attribute = &rest[..attribute_end];
+        rest = rest[attribute_end..].trim_start();
+        rest = rest.strip_prefix('=').ok_or_else(malformed)?.trim_start();
+
+        let quote = rest.chars().next().filter(|&c| c == '"' || c == '\'');
+        let quote = quote.ok_or_else(malformed)?;
+        let value_end = rest[1..].find(quote).ok_or_else(malformed)? + 1;
+        let raw_value = &rest[1..value_end];
+        rest = rest[value_end + 1..].trim_start();
+
+        let value_offset = offset + (raw_value.as_ptr() as usize - tag.as_ptr() as usize) + 1;
+        let value = decode_value(markup, value_offset, raw_value)?;
+
+        if name != "span" {
+            return Err(MarkupError {
+                offset,
+                kind: MarkupErrorKind::UnknownAttribute(name.to_owned(), attribute.to_owned()),
+            });
+        }
+        apply_attribute(&mut attributes, attribute, value)
+            .map_err(|kind| MarkupError { offset, kind })?;
+    }
+
+    Ok((name.to_owned(), attributes))
+}
+
+fn decode_value(markup: &str, offset: usize, value: &str) -> Result<String, MarkupError> {
+    let mut decoded = String::new();
+    let mut position = 0;
+    while let Some(found) = value[position..].find('&') {
+        decoded.push_str(&value[position..position + found]);
+        let (c, length) = decode_entity(markup, offset + position + found)?;
+        decoded.push(c);
+        position += found + length;
+    }
+    decoded.push_str(&value[position..]);
+    Ok(decoded)
+}
+
+fn apply_attribute(
+    attributes: &mut Attributes,
+    name: &str,
+    value: String,
+) -> Result<(), MarkupErrorKind> {
+    let bad = |value: &str| MarkupErrorKind::BadAttributeValue(name.to_owned(), value.to_owned());
+
+    match name {
+        "foreground" | "fgcolor" | "color" => {
+            attributes.foreground = Some(parse_color(&value).ok_or_else(|| bad(&value))?)
+        }
+        "background" | "bgcolor" => {
+            attributes.background = Some(parse_color(&value).ok_or_else(|| bad(&value))?)
+        }
+        "weight" | "font_weight" => {
+            attributes.weight = Some(parse_weight(&value).ok_or_else(|| bad(&value))?)
+        }
+        "style" | "font_style" => {
+            attributes.style = Some(parse_style(&value).ok_or_else(|| bad(&value))?)
+        }
+        "size" | "font_size" => {
+            attributes.size = Some(parse_size(&value).ok_or_else(|| bad(&value))?)
+        }
+        "underline" => {
+            attributes.underline = Some(parse_underline(&value).ok_or_else(|| bad(&value))?)
+        }
+        "rise" => attributes.rise = Some(parse_rise(&value).ok_or_else(|| bad(&value))?),
+        "strikethrough" => {
+            attributes.strikethrough = Some(match value.as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(bad(&value)),
+            })
+        }
+        "font_family" | "face" => attributes.font_family = Some(value),
+        "font" | "font_desc" => attributes.font = Some(value),
+        _ if OTHER_SPAN_ATTRIBUTES.contains(&name) => {
+            attributes.other.insert(name.to_owned(), value);
+        }
+        _ => {
+            return Err(MarkupErrorKind::UnknownAttribute(
+                String::from("span"),
+                name.to_owned(),
+            ))
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_color(value: &str) -> Option<SpanColor> {
+    let hex = match value.strip_prefix('#') {
+        Some(hex) => hex,
+        None if !value.is_empty()
+            && value.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') =>
+        {
+            return Some(SpanColor::Named(value.to_owned()))
+        }
+        None => return None,
+    };
+    if hex.is_empty() || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    // #rgb, #rrggbb, #rrrgggbbb and #rrrrggggbbbb, or the same with an alpha channel.
+    let channels = match hex.len() {
+        3 | 6 | 9 | 12 => 3,
+        4 | 8 | 16 => 4,
+        _ => return None,
+    };
+    let digits = hex.len() / channels;
+    let max = (1u32 << (4 * digits)) - 1;
+    let channel = |index: usize| {
+        let value = u32::from_str_radix(&hex[index * digits..(index + 1) * digits], 16).ok()?;
+        Some(((value * 255 + max / 2) / max) as u8)
+    };
+
+    let alpha = if channels == 4 { channel(3)? } else { 0xff };
+    Some(SpanColor::Rgb(Color::rgba(
+        channel(0)?,
+        channel(1)?,
+        channel(2)?,
+        alpha,
+    )))
+}
+
+fn parse_weight(value: &str) -> Option<FontWeight> {
+    Some(match value {
+        "ultralight" => FontWeight::Ultralight,
+        "light" => FontWeight::Light,
+        "normal" => FontWeight::Normal,
+        "bold" => FontWeight::Bold,
+        "ultrabold" => FontWeight::Ultrabold,
+        "heavy" => FontWeight::Heavy,
+        "thin" => FontWeight::Numeric(100),
+        "semilight" => FontWeight::Numeric(350),
+        "book" => FontWeight::Numeric(380),
+        "medium" => FontWeight::Numeric(500),
+        "semibold" => FontWeight::Numeric(600),
+        "ultraheavy" => FontWeight::Numeric(1000),
+        _ => FontWeight::Numeric(value.parse().ok()?),
+    })
+}
+
+fn parse_style(value: &str) -> Option<FontStyle> {
+    match value {
+        "normal" => Some(FontStyle::Normal),
+        "oblique" => Some(FontStyle::Oblique),
+        "italic" => Some(FontStyle::Italic),
+        _ => None,
+    }
+}
+
+fn parse_size(value: &str) -> Option<FontSize> {
+    Some(match value {
+        "xx-small" => FontSize::XXSmall,
+        "x-small" => FontSize::XSmall,
+        "small" => FontSize::Small,
+        "medium" => FontSize::Medium,
+        "large" => FontSize::Large,
+        "x-large" => FontSize::XLarge,
+        "xx-large" => FontSize::XXLarge,
+        "smaller" => FontSize::Smaller,
+        "larger" => FontSize::Larger,
+        _ => match value.strip_suffix("pt") {
+            Some(points) => FontSize::Points(parse_positive(points)?),
+            None => match value.strip_suffix('%') {
+                Some(percent) => FontSize::Percent(parse_positive(percent)?),
+                None => FontSize::Units(value.parse().ok()?),
+            },
+        },
+    })
+}
+
+fn parse_positive(value: &str) -> Option<f64> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value >= 0.0)
+}
+
+fn parse_underline(value: &str) -> Option<Underline> {
+    match value {
+        "none" => Some(Underline::None),
+        "single" => Some(Underline::Single),
+        "double" => Some(Underline::Double),
+        "low" => Some(Underline::Low),
+        "error" => Some(Underline::Error),
+        _ => None,
+    }
+}
+
+// Plain numbers are in Pango units (1024ths of a point); values in points are converted.
+fn parse_rise(value: &str) -> Option<i32> {
+    match value.strip_suffix("pt") {
+        Some(points) => {
+            let points = points.parse::<f64>().ok()?;
+            let rise = (points * 1024.0).round();
+            if rise.abs() <= f64::from(i32::MAX) {
+                Some(rise as i32)
+            } else {
+                None
+            }
+        }
+        None => value.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(text: &str, attributes: Attributes) -> TextRun {
+        TextRun {
+            text: String::from(text),
+            attributes,
+        }
+    }
+
+    fn error(markup: &str) -> MarkupError {
+        parse_markup(markup).expect_err("Expected markup to be rejected")
+    }
+
+    #[test]
+    fn it_parses_plain_text_and_entities() {
+        assert_eq!(parse_markup("").unwrap(), vec![]);
+        assert_eq!(
+            parse_markup("Tom &amp; Jerry &lt;3 &#65;&#x42;&quot;&apos;&gt;").unwrap(),
+            vec![run("Tom & Jerry <3 AB\"'>", Attributes::default())]
+        );
+    }
+
+    #[test]
+    fn it_skips_comments_and_processing_instructions() {
+        assert_eq!(
+            parse_markup("a<!-- c <b> -->b<?pi x?>c").unwrap(),
+            vec![run("abc", Attributes::default())]
+        );
+        assert_eq!(
+            parse_markup("<i>a<!---->b</i>").unwrap(),
+            vec![run(
+                "ab",
+                Attributes {
+                    style: Some(FontStyle::Italic),
+                    ..Attributes::default()
+                }
+            )]
+        );
+        assert_eq!(
+            error("a<!-- b"),
+            MarkupError {
+                offset: 1,
+                kind: MarkupErrorKind::MalformedTag,
+            }
+        );
+        assert_eq!(error("<?pi").kind, MarkupErrorKind::MalformedTag);
+    }
+
+    #[test]
+    fn it_resolves_nested_attributes() {
+        let runs = parse_markup(
+            "a<span foreground='#f00' size=\"x-large\">b<b>c<span fgcolor=\"blue\" \
+             size='12.5pt' weight='600'>d</span></b></span>e",
+        )
+        .unwrap();
+
+        let red = Attributes {
+            foreground: Some(SpanColor::Rgb(Color::rgb(0xff, 0, 0))),
+            size: Some(FontSize::XLarge),
+            ..Attributes::default()
+        };
+        let bold = Attributes {
+            weight: Some(FontWeight::Bold),
+            ..red.clone()
+        };
+        let blue = Attributes {
+            foreground: Some(SpanColor::Named(String::from("blue"))),
+            size: Some(FontSize::Points(12.5)),
+            weight: Some(FontWeight::Numeric(600)),
+            ..red.clone()
+        };
+
+        assert_eq!(
+            runs,
+            vec![
+                run("a", Attributes::default()),
+                run("b", red),
+                run("c", bold),
+                run("d", blue),
+                run("e", Attributes::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_applies_shortcut_tags() {
+        let runs = parse_markup("<i>x</i><sub>2</sub><tt><u>y</u></tt><s/>z").unwrap();
+
+        assert_eq!(runs[0].attributes.style, Some(FontStyle::Italic));
+        assert_eq!(runs[1].attributes.rise, Some(-5000));
+        assert_eq!(runs[1].attributes.size, Some(FontSize::Smaller));
+        assert_eq!(runs[2].attributes.font_family.as_deref(), Some("Monospace"));
+        assert_eq!(runs[2].attributes.underline, Some(Underline::Single));
+        assert_eq!(runs[3], run("z", Attributes::default()));
+    }
+
+    #[test]
+    fn it_merges_runs_with_equal_attributes() {
+        assert_eq!(
+            parse_markup("<b>a</b><b>b</b>c<span>d</span>").unwrap(),
+            vec![
+                run(
+                    "ab",
+                    Attributes {
+                        weight: Some(FontWeight::Bold),
+                        ..Attributes::default()
+                    }
+                ),
+                run("cd", Attributes::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_attribute_values() {
+        let runs = parse_markup(
+            "<span background='#12345678' rise='-2pt' underline='error' strikethrough='true' \
+             font_family='A &amp; B' font='Sans 10' letter_spacing='1024' style='oblique'>x</span>",
+        )
+        .unwrap();
+        let attributes = &runs[0].attributes;
+
+        assert_eq!(
+            attributes.background,
+            Some(SpanColor::Rgb(Color::rgba(0x12, 0x34, 0x56, 0x78)))
+        );
+        assert_eq!(attributes.rise, Some(-2048));
+        assert_eq!(attributes.underline, Some(Underline::Error));
+        assert_eq!(attributes.strikethrough, Some(true));
+        assert_eq!(attributes.font_family.as_deref(), Some("A & B"));
+        assert_eq!(attributes.font.as_deref(), Some("Sans 10"));
+        assert_eq!(attributes.style, Some(FontStyle::Oblique));
+        assert_eq!(attributes.other.get("letter_spacing").unwrap(), "1024");
+
+        let runs = parse_markup("<span color='#fff' size='150%'>x</span>").unwrap();
+        assert_eq!(
+            runs[0].attributes.foreground,
+            Some(SpanColor::Rgb(Color::rgb(0xff, 0xff, 0xff)))
+        );
+        assert_eq!(runs[0].attributes.size, Some(FontSize::Percent(150.0)));
+    }
+
+    #[test]
+    fn it_reports_unbalanced_tags() {
+        assert_eq!(
+            error("ab<b>c</i>"),
+            MarkupError {
+                offset: 6,
+                kind: MarkupErrorKind::UnbalancedTag(Some(String::from("b")), String::from("i")),
+            }
+        );
+        assert_eq!(
+            error("x</b>").kind,
+            MarkupErrorKind::UnbalancedTag(None, String::from("b"))
+        );
+        assert_eq!(
+            error("a<i><b>c</b>"),
+            MarkupError {
+                offset: 1,
+                kind: MarkupErrorKind::UnclosedTag(String::from("i")),
+            }
+        );
+        assert_eq!(error("a <b").kind, MarkupErrorKind::MalformedTag);
+        assert_eq!(error("<>x</>").kind, MarkupErrorKind::MalformedTag);
+    }
+
+    #[test]
+    fn it_reports_unknown_tags_and_attributes() {
+        assert_eq!(
+            error("a<blink>b</blink>"),
+            MarkupError {
+                offset: 1,
+                kind: MarkupErrorKind::UnknownTag(String::from("blink")),
+            }
+        );
+        assert_eq!(
+            error("<span href='x'>b</span>").kind,
+            MarkupErrorKind::UnknownAttribute(String::from("span"), String::from("href"))
+        );
+        assert_eq!(
+            error("<b weight='bold'>b</b>").kind,
+            MarkupErrorKind::UnknownAttribute(String::from("b"), String::from("weight"))
+        );
+        assert_eq!(
+            error("<span weight>b</span>").kind,
+            MarkupErrorKind::MalformedTag
+        );
+    }
+
+    #[test]
+    fn it_reports_bad_attribute_values() {
+        for &(markup, attribute, value) in &[
+            ("<span foreground='#12'>x</span>", "foreground", "#12"),
+            ("<span weight='heavier'>x</span>", "weight", "heavier"),
+            ("<span size='big'>x</span>", "size", "big"),
+            ("<span rise='up'>x</span>", "rise", "up"),
+            ("<span strikethrough='yes'>x</span>", "strikethrough", "yes"),
+            (
+                "<span background='not-a-color'>x</span>",
+                "background",
+                "not-a-color",
+            ),
+        ] {
+            assert_eq!(
+                error(markup),
+                MarkupError {
+                    offset: 0,
+                    kind: MarkupErrorKind::BadAttributeValue(
+                        String::from(attribute),
+                        String::from(value)
+                    ),
+                },
+                "{}",
+                markup
+            );
+        }
+    }
+
+    #[test]
+    fn it_reports_bad_entities() {
+        assert_eq!(
+            error("a &b"),
+            MarkupError {
+                offset: 2,
+                kind: MarkupErrorKind::BadEntity(String::from("b")),
+            }
+        );
+        assert_eq!(
+            error("Tom & Jerry").kind,
+            MarkupErrorKind::BadEntity(String::new())
+        );
+        assert_eq!(
+            error("&nbsp;").kind,
+            MarkupErrorKind::BadEntity(String::from("nbsp"))
+        );
+        assert_eq!(
+            error("&#0;").kind,
+            MarkupErrorKind::BadEntity(String::from("#0"))
+        );
+        assert_eq!(
+            error("<span font='a &x; b'>c</span>"),
+            MarkupError {
+                offset: 14,
+                kind: MarkupErrorKind::BadEntity(String::from("x")),
+            }
+        );
+        assert_eq!(
+            error("&amp;&#xD800;").to_string(),
+            "bad entity &#xD800; at byte 5"
+        );
+    }
+}
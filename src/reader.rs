@@ -0,0 +1,492 @@
+extern crate serde_json;
+
+use std::collections::VecDeque;
+use std::io::{BufRead, ErrorKind};
+use super::{Block, BlockRef, ClickEvent, Header, ParseError, ParseMode};
+use framing::{ArrayFraming, Frame, LineBuffer, StatusLineFraming};
+
+// Lines are split by the same `LineBuffer` as in the decoder and the async streams, so that the
+// length limit and the handling of invalid UTF-8 are the same for every reader.
+fn read_line<R: BufRead>(
+    reader: &mut R,
+    lines: &mut LineBuffer,
+) -> Result<Option<String>, ParseError> {
+    loop {
+        if let Some(line) = lines.next_line() {
+            return line.map(Some);
+        }
+
+        let length = match reader.fill_buf() {
+            Ok(chunk) => {
+                lines.extend(chunk);
+                chunk.len()
+            }
+            Err(ref error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        };
+        if length == 0 {
+            return Ok(lines.take_remaining());
+        }
+        reader.consume(length);
+    }
+}
+
+// Input is read line by line. Status lines usually fit on a single input line, as i3status and
+// most other status commands write them, but pretty-printed ones are joined back together.
+pub struct StatusLineReader<R> {
+    reader: R,
+    header: Header,
+    framing: StatusLineFraming,
+    frames: VecDeque<Result<Frame, ParseError>>,
+    lines: LineBuffer,
+    buffer: String,
+}
+
+impl<R: BufRead> StatusLineReader<R> {
+    pub fn new(reader: R) -> Result<StatusLineReader<R>, ParseError> {
+        StatusLineReader::with_mode(reader, ParseMode::Normal)
+    }
+
+    pub fn with_mode(mut reader: R, mode: ParseMode) -> Result<StatusLineReader<R>, ParseError> {
+        let mut framing = StatusLineFraming::with_mode(mode);
+        let mut frames = VecDeque::new();
+        let mut lines = LineBuffer::new();
+
+        while frames.is_empty() {
+            match read_line(&mut reader, &mut lines)? {
+                Some(line) => framing.push_line(&line, &mut frames),
+                None => {
+                    return Err(ParseError::ReadError(String::from(
+                        "Stream ended before header",
+                    )))
+                }
+            }
+        }
+
+        let header = match frames.pop_front() {
+            Some(Ok(Frame::Header(header))) => header,
+            Some(Err(error)) => return Err(error),
+            _ => unreachable!("first frame is always the header"),
+        };
+
+        Ok(StatusLineReader {
+            reader,
+            header,
+            framing,
+            frames,
+            lines,
+            buffer: String::new(),
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn is_plain_text(&self) -> bool {
+        self.framing.is_plain_text()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // Like `next`, but the blocks borrow their text from the reader's line buffer where they
+    // can, so the returned line has to be dropped before the next one is read. Status lines that
+    // the lenient parse mode had to rewrite are always owned.
+    pub fn next_borrowed(&mut self) -> Option<Result<Vec<BlockRef<'_>>, ParseError>> {
+        loop {
+            match self.frames.pop_front() {
+                Some(Ok(Frame::StatusLine(blocks))) => {
+                    return Some(Ok(blocks.into_iter().map(BlockRef::from).collect()))
+                }
+                Some(Ok(Frame::Header(_))) => continue,
+                Some(Err(error)) => return Some(Err(error)),
+                None => {}
+            }
+
+            if self.framing.is_closed() {
+                return None;
+            }
+
+            match read_line(&mut self.reader, &mut self.lines) {
+                Ok(Some(line)) => self.buffer = line,
+                Ok(None) => return self.framing.finish().map(Err),
+                Err(error) => return Some(Err(error)),
+            }
+
+            // The buffer is only borrowed once a line is known to contain a status line, as
+            // it cannot be refilled while a returned line still points into it.
+            if !self.framing.is_padding(&self.buffer) {
+                break;
+            }
+            self.framing.push_line(&self.buffer, &mut self.frames);
+        }
+
+        let mut frames = VecDeque::new();
+        self.framing.push_line(&self.buffer, &mut frames);
+        let first = frames.pop_front();
+
+        // Any further status lines on the same input line are kept as owned blocks.
+        for frame in frames {
+            self.frames.push_back(frame.map(|frame| match frame {
+                Frame::Header(header) => Frame::Header(header),
+                Frame::StatusLine(blocks) => {
+                    Frame::StatusLine(blocks.into_iter().map(BlockRef::into_owned).collect())
+                }
+            }));
+        }
+
+        match first {
+            Some(Ok(Frame::StatusLine(blocks))) => Some(Ok(blocks)),
+            Some(Err(error)) => Some(Err(error)),
+            _ => unreachable!("lines that are not padding always produce a status line"),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StatusLineReader<R> {
+    type Item = Result<Vec<Block>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.frames.pop_front() {
+                Some(Ok(Frame::StatusLine(blocks))) => return Some(Ok(blocks)),
+                Some(Ok(Frame::Header(_))) => continue,
+                Some(Err(error)) => return Some(Err(error)),
+                None => {}
+            }
+
+            if self.framing.is_closed() {
+                return None;
+            }
+
+            match read_line(&mut self.reader, &mut self.lines) {
+                Ok(Some(line)) => self.framing.push_line(&line, &mut self.frames),
+                Ok(None) => return self.framing.finish().map(Err),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+pub struct ClickEventReader<R> {
+    reader: R,
+    framing: ArrayFraming,
+    events: VecDeque<Result<ClickEvent, ParseError>>,
+    lines: LineBuffer,
+}
+
+impl<R: BufRead> ClickEventReader<R> {
+    pub fn new(reader: R) -> ClickEventReader<R> {
+        ClickEventReader::with_mode(reader, ParseMode::Normal)
+    }
+
+    pub fn with_mode(reader: R, mode: ParseMode) -> ClickEventReader<R> {
+        ClickEventReader {
+            reader,
+            framing: ArrayFraming::click_events(mode),
+            events: VecDeque::new(),
+            lines: LineBuffer::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for ClickEventReader<R> {
+    type Item = Result<ClickEvent, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Some(event);
+            }
+
+            if self.framing.is_closed() {
+                return None;
+            }
+
+            match read_line(&mut self.reader, &mut self.lines) {
+                Ok(Some(line)) => {
+                    let events = &mut self.events;
+                    self.framing
+                        .elements(&line, |event| events.push_back(event));
+                }
+                Ok(None) => return self.framing.finish().map(Err),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::MouseButton;
+
+    #[test]
+    fn it_reads_header_and_status_lines() {
+        let input = r#"{"version":1,"click_events":true}
+[
+[{"full_text":"first"}]
+,[{"full_text":"second"},{"full_text":"third"}]
+"#;
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+        assert_eq!(
+            reader.header(),
+            &"{\"version\":1,\"click_events\":true}".parse().unwrap()
+        );
+
+        let line = reader.next().unwrap().expect("Could not parse first line");
+        assert_eq!(line.len(), 1);
+        assert_eq!(line[0].full_text(), "first");
+
+        let line = reader.next().unwrap().expect("Could not parse second line");
+        assert_eq!(line.len(), 2);
+        assert_eq!(line[1].full_text(), "third");
+
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn it_tolerates_trailing_commas() {
+        let input =
+            "{\"version\":1}\n[[{\"full_text\":\"a\"}],\n[],\n[{\"full_text\":\"b\"}],\n]\n";
+        let lines: Vec<_> = StatusLineReader::new(input.as_bytes())
+            .expect("Could not read header")
+            .collect::<Result<_, _>>()
+            .expect("Could not parse lines");
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].full_text(), "a");
+        assert!(lines[1].is_empty());
+        assert_eq!(lines[2][0].full_text(), "b");
+    }
+
+    #[test]
+    fn it_reports_bad_lines_and_continues() {
+        let input = "{\"version\":1}\n[\n[{\"nope\":1}]\n,[{\"full_text\":\"ok\"}]\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+
+        assert!(reader.next().unwrap().is_err());
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "ok");
+    }
+
+    #[test]
+    fn it_reads_pretty_printed_status_lines() {
+        let input =
+            "{\"version\":1}\n[\n[\n  {\"full_text\":\"a\"}\n],\n[{\"full_text\":\"b\"}]\n[\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "a");
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "b");
+        assert_eq!(reader.next().unwrap().unwrap_err().raw_line(), Some("["));
+        assert!(reader.next().is_none());
+
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+        {
+            let line = reader.next_borrowed().unwrap().expect("Could not parse");
+            assert_eq!(line[0].full_text(), "a");
+            assert!(!line[0].is_borrowed());
+        }
+        {
+            let line = reader.next_borrowed().unwrap().expect("Could not parse");
+            assert_eq!(line[0].full_text(), "b");
+            assert!(line[0].is_borrowed());
+        }
+        assert!(reader.next_borrowed().unwrap().is_err());
+        assert!(reader.next_borrowed().is_none());
+    }
+
+    #[test]
+    fn it_reads_borrowed_lines() {
+        let input = "{\"version\":1}\n[\n\n[{\"full_text\":\"a\",\"name\":\"x\"}],[{\"full_text\":\"\\u00e9\"}]\n,[{\"nope\":1}]\n]\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+
+        {
+            let line = reader
+                .next_borrowed()
+                .unwrap()
+                .expect("Could not parse line");
+            assert_eq!(line[0].full_text(), "a");
+            assert_eq!(line[0].name(), Some("x"));
+            assert!(line[0].is_borrowed());
+        }
+
+        let line = reader
+            .next_borrowed()
+            .unwrap()
+            .expect("Could not parse line");
+        assert_eq!(line[0].full_text(), "\u{e9}");
+        assert!(!line[0].is_borrowed());
+
+        assert!(reader.next_borrowed().unwrap().is_err());
+        assert!(reader.next_borrowed().is_none());
+    }
+
+    #[test]
+    fn it_reads_borrowed_lines_with_parse_modes() {
+        let input = "{\"version\":1}\n[\n[{\"full_text\":\"a\"}]\n,[{\"full_text\":\"b\",\"align\":\"middle\"}]\n";
+
+        for &mode in &[ParseMode::Strict, ParseMode::Lenient] {
+            let mut reader =
+                StatusLineReader::with_mode(input.as_bytes(), mode).expect("Could not read header");
+            {
+                let line = reader.next_borrowed().unwrap().expect("Could not parse");
+                assert_eq!(line[0].full_text(), "a");
+                assert!(line[0].is_borrowed());
+            }
+
+            match mode {
+                ParseMode::Lenient => {
+                    let line = reader.next_borrowed().unwrap().expect("Could not parse");
+                    assert_eq!(line[0].full_text(), "b");
+                    assert!(!line[0].is_borrowed());
+                }
+                _ => assert!(reader.next_borrowed().unwrap().is_err()),
+            }
+        }
+    }
+
+    #[test]
+    fn it_reads_with_parse_modes() {
+        let input = "{\"version\":1}\n[\n[{\"full_text\":\"a\",\"align\":\"middle\",\"color\":\"red\"}]\n,[{\"full_text\":\"b\",\"colour\":1}]\n";
+
+        let mut reader = StatusLineReader::with_mode(input.as_bytes(), ParseMode::Lenient)
+            .expect("Could not read header");
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "a");
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "b");
+
+        let mut reader = StatusLineReader::with_mode(input.as_bytes(), ParseMode::Strict)
+            .expect("Could not read header");
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().unwrap().is_err());
+
+        let header = "{\"version\":1,\"clicks\":true}\n[\n";
+        assert!(StatusLineReader::with_mode(header.as_bytes(), ParseMode::Strict).is_err());
+        assert!(StatusLineReader::with_mode(header.as_bytes(), ParseMode::Normal).is_ok());
+
+        let events = "[\n{\"name\":\"a\",\"button\":\"1\"}\n";
+        let event = ClickEventReader::with_mode(events.as_bytes(), ParseMode::Lenient)
+            .next()
+            .unwrap()
+            .expect("Could not parse click event");
+        assert_eq!(event.button(), MouseButton::Left);
+    }
+
+    #[test]
+    fn it_reads_pretty_printed_headers() {
+        let input = "{\n  \"version\": 1\n}\n[\n[{\"full_text\":\"a\"}]\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+        assert!(!reader.is_plain_text());
+        assert_eq!(reader.header().version(), 1);
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "a");
+    }
+
+    #[test]
+    fn it_splits_lines_like_the_decoder() {
+        let mut input = b"{\"version\":1}\n[\n[{\"full_text\":\"\xff\"}]\n".to_vec();
+        input.extend(vec![b' '; 2 * 1024 * 1024]);
+        input.extend(b"\n[{\"full_text\":\"ok\"}]");
+        let mut reader = StatusLineReader::new(&input[..]).expect("Could not read header");
+
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "\u{fffd}");
+        assert!(reader.next().unwrap().is_err());
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "ok");
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn it_fails_without_header() {
+        assert!(StatusLineReader::new("".as_bytes()).is_err());
+        assert!(StatusLineReader::new("\n\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn it_reads_plain_text_streams() {
+        let input = "CPU: 5%\nCPU: 12% & rising\n\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+        assert!(reader.is_plain_text());
+        assert_eq!(reader.header(), &Header::default());
+
+        let lines: Vec<Vec<Block>> = reader.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].len(), 1);
+        assert_eq!(lines[0][0].full_text(), "CPU: 5%");
+        assert_eq!(lines[1][0].full_text(), "CPU: 12% & rising");
+        assert_eq!(lines[2][0].full_text(), "");
+    }
+
+    #[test]
+    fn it_resynchronizes_after_garbage() {
+        let input = "{\"version\":1}\n[\n[{\"full_text\":\"a\"}]\nDEBUG: oops\n,[{\"full_text\":\"trunc\n,[{\"full_text\":\"b\"}]\n";
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "a");
+
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.raw_line(), Some("DEBUG: oops"));
+
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            error.raw_line(),
+            Some("[{\"full_text\":\"trunc\n,[{\"full_text\":\"b\"}]")
+        );
+
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "b");
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn it_skips_bad_elements_as_a_whole() {
+        let input = "{\"version\":1}\n[\n,[{\"full_text\":\"b\",\"min_width\":-1,\"_hist\":[]}]\n,[{\"full_text\":\"[]\",\"color\":\"red\"}]\n,[{\"full_text\":\"ok\"}]\n";
+
+        let mut reader = StatusLineReader::new(input.as_bytes()).expect("Could not read header");
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().unwrap().is_err());
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "ok");
+        assert!(reader.next().is_none());
+
+        let input = "{\"version\":1}\n[\n,[{\"full_text\":\"b\",\"colour\":[[]]}]\n";
+        let mut reader = StatusLineReader::with_mode(input.as_bytes(), ParseMode::Strict)
+            .expect("Could not read header");
+        assert!(reader.next().unwrap().is_err());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn it_reads_click_events() {
+        let input = r#"[
+{"name":"ethernet","instance":"eth0","button":1,"x":1320,"y":1400}
+,{"name":"wifi","button":3}
+,{"name":"broken"
+,{"name":"disk","instance":"/","button":4}
+"#;
+        let mut reader = ClickEventReader::new(input.as_bytes());
+
+        let event = reader.next().unwrap().expect("Could not parse first event");
+        assert_eq!(event.name(), "ethernet");
+        assert_eq!(event.instance(), Some("eth0"));
+        assert_eq!(event.coordinates(), Some((1320, 1400)));
+
+        let event = reader
+            .next()
+            .unwrap()
+            .expect("Could not parse second event");
+        assert_eq!(event.name(), "wifi");
+        assert_eq!(event.button(), MouseButton::Right);
+
+        assert!(reader.next().unwrap().is_err());
+
+        let event = reader
+            .next()
+            .unwrap()
+            .expect("Could not parse event after error");
+        assert_eq!(event.name(), "disk");
+
+        assert!(reader.next().is_none());
+    }
+}
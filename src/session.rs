@@ -0,0 +1,359 @@
+extern crate libc;
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use super::{
+    Block, ClickEvent, ClickEventReader, ClickEventWriter, Header, ParseError, StatusLineReader,
+    StatusLineWriter,
+};
+
+pub struct StatusCommandSession<W: Write> {
+    writer: StatusLineWriter<W>,
+    header: Header,
+    click_events: Option<Receiver<Result<ClickEvent, ParseError>>>,
+}
+
+impl StatusCommandSession<io::Stdout> {
+    pub fn new(header: Header) -> io::Result<StatusCommandSession<io::Stdout>> {
+        StatusCommandSession::with_io(io::stdout(), BufReader::new(io::stdin()), header)
+    }
+}
+
+impl<W: Write> StatusCommandSession<W> {
+    pub fn with_io<R>(writer: W, reader: R, header: Header) -> io::Result<StatusCommandSession<W>>
+    where
+        R: BufRead + Send + 'static,
+    {
+        let writer = StatusLineWriter::new(writer, &header)?;
+
+        let click_events = if header.click_events() {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for event in ClickEventReader::new(reader) {
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+            });
+            Some(receiver)
+        } else {
+            None
+        };
+
+        Ok(StatusCommandSession {
+            writer,
+            header,
+            click_events,
+        })
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    pub fn write_line(&mut self, blocks: &[Block]) -> io::Result<()> {
+        self.writer.write_line(blocks)
+    }
+
+    // Only available when click events were enabled in the header.
+    pub fn click_events(&self) -> Option<&Receiver<Result<ClickEvent, ParseError>>> {
+        self.click_events.as_ref()
+    }
+
+    pub fn close(self) -> io::Result<W> {
+        self.writer.close()
+    }
+}
+
+// The status command is killed and reaped when the session is dropped, so it does not outlive the
+// session or linger as a zombie. It runs in its own process group, so that signals also reach the
+// processes it started, as they do with i3bar.
+type StatusLines = StatusLineReader<BufReader<ChildStdout>>;
+
+pub struct BarSession {
+    child: Child,
+    status_lines: StatusLines,
+    click_events: Option<ClickEventWriter<ChildStdin>>,
+}
+
+impl BarSession {
+    pub fn spawn(command: &mut Command) -> Result<BarSession, ParseError> {
+        let mut child = command
+            .process_group(0)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stdin = child.stdin.take().expect("stdin is piped");
+
+        match BarSession::connect(stdout, stdin) {
+            Ok((status_lines, click_events)) => Ok(BarSession {
+                child,
+                status_lines,
+                click_events,
+            }),
+            Err(error) => {
+                kill_group(&mut child);
+                Err(error)
+            }
+        }
+    }
+
+    fn connect(
+        stdout: ChildStdout,
+        stdin: ChildStdin,
+    ) -> Result<(StatusLines, Option<ClickEventWriter<ChildStdin>>), ParseError> {
+        let status_lines = StatusLineReader::new(BufReader::new(stdout))?;
+
+        let version = status_lines.header().version();
+        if version != 1 {
+            return Err(ParseError::InvalidData(format!(
+                "Unsupported protocol version {}",
+                version
+            )));
+        }
+
+        let click_events = if status_lines.header().click_events() {
+            Some(ClickEventWriter::new(stdin)?)
+        } else {
+            None
+        };
+
+        Ok((status_lines, click_events))
+    }
+
+    pub fn header(&self) -> &Header {
+        self.status_lines.header()
+    }
+
+    pub fn child(&self) -> &Child {
+        &self.child
+    }
+
+    pub fn child_mut(&mut self) -> &mut Child {
+        &mut self.child
+    }
+
+    pub fn send_click(&mut self, event: &ClickEvent) -> io::Result<()> {
+        match self.click_events {
+            Some(ref mut writer) => writer.write_event(event),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Status command did not enable click events",
+            )),
+        }
+    }
+
+    pub fn pause(&mut self) -> io::Result<()> {
+        let signal = self.header().stop_signal();
+        self.signal(signal)
+    }
+
+    pub fn resume(&mut self) -> io::Result<()> {
+        let signal = self.header().continue_signal();
+        self.signal(signal)
+    }
+
+    fn signal(&mut self, signal: i32) -> io::Result<()> {
+        // Once the child has been reaped its pid may belong to an unrelated process.
+        if self.child.try_wait()?.is_some() {
+            return Err(io::Error::other("Status command has already exited"));
+        }
+
+        signal_group(&self.child, signal)
+    }
+}
+
+fn signal_group(child: &Child, signal: i32) -> io::Result<()> {
+    // A negative pid addresses the whole process group the child leads.
+    let result = unsafe { libc::kill(-(child.id() as libc::pid_t), signal) };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn kill_group(child: &mut Child) {
+    if let Ok(None) = child.try_wait() {
+        let _ = signal_group(child, libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+impl Drop for BarSession {
+    fn drop(&mut self) {
+        kill_group(&mut self.child);
+    }
+}
+
+impl Iterator for BarSession {
+    type Item = Result<Vec<Block>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.status_lines.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Cursor;
+    use std::time::Duration;
+    use super::super::{
+        BlockBuilder, ClickEventBuilder, HeaderBuilder, MouseButton, StatusLineReader,
+    };
+
+    #[test]
+    fn it_writes_header_and_lines() {
+        let header = HeaderBuilder::new().build();
+        let mut session =
+            StatusCommandSession::with_io(Vec::new(), Cursor::new(Vec::new()), header).unwrap();
+        assert!(session.click_events().is_none());
+
+        session
+            .write_line(&[BlockBuilder::new("hello").build()])
+            .unwrap();
+        let output = session.close().unwrap();
+
+        let mut reader = StatusLineReader::new(&output[..]).unwrap();
+        assert_eq!(reader.header(), &header);
+        assert_eq!(reader.next().unwrap().unwrap()[0].full_text(), "hello");
+    }
+
+    #[test]
+    fn it_delivers_click_events() {
+        let header = HeaderBuilder::new().click_events(true).build();
+        let input = "[\n{\"name\":\"a\",\"button\":1}\n,{\"name\":\"b\",\"button\":3}\n";
+        let session =
+            StatusCommandSession::with_io(Vec::new(), Cursor::new(input), header).unwrap();
+
+        let events: Vec<ClickEvent> = session
+            .click_events()
+            .expect("Click events not enabled")
+            .iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name(), "a");
+        assert_eq!(events[1].button(), MouseButton::Right);
+    }
+
+    // The single-letter process state from /proc, e.g. `T` for a stopped process.
+    fn process_state(pid: u32) -> Option<char> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        stat[stat.rfind(')')? + 1..].trim_start().chars().next()
+    }
+
+    // Signals are delivered asynchronously, so the state is polled for a while.
+    fn wait_for_state<F: Fn(Option<char>) -> bool>(pid: u32, expected: F) -> bool {
+        (0..200).any(|_| {
+            let reached = expected(process_state(pid));
+            if !reached {
+                thread::sleep(Duration::from_millis(10));
+            }
+            reached
+        })
+    }
+
+    fn shell(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        command
+    }
+
+    #[test]
+    fn it_drives_a_status_command() {
+        let script = r#"
+            echo '{"version":1,"click_events":true}'
+            echo '['
+            echo '[{"full_text":"waiting"}]'
+            read opening
+            read click
+            echo ',[{"full_text":"clicked"}]'
+        "#;
+        let mut session = BarSession::spawn(&mut shell(script)).expect("Could not spawn");
+        assert!(session.header().click_events());
+
+        assert_eq!(session.next().unwrap().unwrap()[0].full_text(), "waiting");
+
+        session
+            .send_click(&ClickEventBuilder::new("a", MouseButton::Left).build())
+            .unwrap();
+        assert_eq!(session.next().unwrap().unwrap()[0].full_text(), "clicked");
+
+        assert!(session.next().is_none());
+        session.child_mut().wait().unwrap();
+    }
+
+    #[test]
+    fn it_pauses_and_resumes_the_child() {
+        let script = r#"
+            echo '{"version":1}'
+            echo '['
+            echo '[{"full_text":"tick"}]'
+            sleep 10
+        "#;
+        let mut session = BarSession::spawn(&mut shell(script)).expect("Could not spawn");
+        assert_eq!(session.next().unwrap().unwrap()[0].full_text(), "tick");
+
+        let pid = session.child().id();
+
+        session.pause().unwrap();
+        assert!(wait_for_state(pid, |state| state == Some('T')));
+        session.resume().unwrap();
+        assert!(wait_for_state(pid, |state| state.is_some() && state != Some('T')));
+        assert!(session
+            .send_click(&ClickEventBuilder::new("a", MouseButton::Left).build())
+            .is_err());
+
+        session.child_mut().kill().unwrap();
+        session.child_mut().wait().unwrap();
+        assert!(session.pause().is_err());
+    }
+
+    #[test]
+    fn it_kills_the_child_when_dropped() {
+        let script = r#"
+            echo '{"version":1}'
+            echo '['
+            sleep 10 &
+            echo "[{\"full_text\":\"$!\"}]"
+            wait
+        "#;
+        let mut session = BarSession::spawn(&mut shell(script)).expect("Could not spawn");
+        let pid = session.child().id();
+        let grandchild: u32 = session.next().unwrap().unwrap()[0]
+            .full_text()
+            .parse()
+            .expect("Could not read pid of grandchild");
+        assert!(process_state(grandchild).is_some());
+
+        drop(session);
+        assert_eq!(process_state(pid), None);
+        // Nothing might reap the orphaned grandchild in a container, so a zombie is good enough.
+        assert!(wait_for_state(grandchild, |state| state.is_none() || state == Some('Z')));
+    }
+
+    #[test]
+    fn it_rejects_invalid_headers() {
+        assert!(BarSession::spawn(&mut shell("echo '{\"version\":2}'")).is_err());
+        assert!(BarSession::spawn(&mut shell("true")).is_err());
+    }
+
+    #[test]
+    fn it_accepts_plain_text_commands() {
+        let mut session =
+            BarSession::spawn(&mut shell("echo 'not json'")).expect("Could not spawn");
+        assert!(!session.header().click_events());
+        assert_eq!(session.next().unwrap().unwrap()[0].full_text(), "not json");
+        assert!(session.next().is_none());
+        session.child_mut().wait().unwrap();
+    }
+}
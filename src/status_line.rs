@@ -0,0 +1,296 @@
+extern crate serde_json;
+
+use std::iter::FromIterator;
+use std::slice;
+use std::str::FromStr;
+use std::vec;
+use serde::Deserialize;
+use super::{Block, ClickEvent, ParseError, ParseMode, StatusLineDiff, Warning};
+use mode;
+use validation;
+
+// Blocks are identified by their (name, instance) pair, the same way click events refer to them.
+// Blocks without a name cannot be looked up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StatusLine {
+    blocks: Vec<Block>,
+}
+
+impl FromStr for StatusLine {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> Result<StatusLine, ParseError> {
+        serde_json::from_str(str).map_err(Into::into)
+    }
+}
+
+impl StatusLine {
+    pub fn new() -> StatusLine {
+        StatusLine::default()
+    }
+
+    pub fn from_str_mode(str: &str, mode: ParseMode) -> Result<StatusLine, ParseError> {
+        if mode == ParseMode::Normal {
+            return str.parse();
+        }
+
+        let mut value = serde_json::from_str(str)?;
+        mode::apply_to_status_line(&mut value, mode)?;
+        StatusLine::deserialize(value).map_err(Into::into)
+    }
+
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn into_blocks(self) -> Vec<Block> {
+        self.blocks
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Block> {
+        self.blocks.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, Block> {
+        self.blocks.iter_mut()
+    }
+
+    pub fn push(&mut self, block: Block) {
+        self.blocks.push(block);
+    }
+
+    pub fn position(&self, name: &str, instance: Option<&str>) -> Option<usize> {
+        self.blocks
+            .iter()
+            .position(|block| block.name() == Some(name) && block.instance() == instance)
+    }
+
+    pub fn contains(&self, name: &str, instance: Option<&str>) -> bool {
+        self.position(name, instance).is_some()
+    }
+
+    pub fn get(&self, name: &str, instance: Option<&str>) -> Option<&Block> {
+        self.position(name, instance)
+            .map(|index| &self.blocks[index])
+    }
+
+    pub fn get_mut(&mut self, name: &str, instance: Option<&str>) -> Option<&mut Block> {
+        match self.position(name, instance) {
+            Some(index) => Some(&mut self.blocks[index]),
+            None => None,
+        }
+    }
+
+    pub fn clicked_block(&self, event: &ClickEvent) -> Option<&Block> {
+        self.get(event.name(), event.instance())
+    }
+
+    pub fn validate(&self) -> Vec<Warning> {
+        validation::validate_blocks(&self.blocks)
+    }
+
+    // Also reports values that would make parsing a `StatusLine` fail, such as invalid colors.
+    pub fn validate_json(json: &str) -> Result<Vec<Warning>, ParseError> {
+        validation::validate_json(json)
+    }
+
+    // Changes needed to get from self to next.
+    pub fn diff(&self, next: &StatusLine) -> StatusLineDiff {
+        StatusLineDiff::new(&self.blocks, &next.blocks)
+    }
+
+    // The following leave the line untouched when no block matches the key.
+
+    pub fn replace(&mut self, name: &str, instance: Option<&str>, block: Block) -> Option<Block> {
+        self.position(name, instance)
+            .map(|index| ::std::mem::replace(&mut self.blocks[index], block))
+    }
+
+    pub fn insert_before(&mut self, name: &str, instance: Option<&str>, block: Block) -> bool {
+        match self.position(name, instance) {
+            Some(index) => {
+                self.blocks.insert(index, block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn insert_after(&mut self, name: &str, instance: Option<&str>, block: Block) -> bool {
+        match self.position(name, instance) {
+            Some(index) => {
+                self.blocks.insert(index + 1, block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, name: &str, instance: Option<&str>) -> Option<Block> {
+        self.position(name, instance)
+            .map(|index| self.blocks.remove(index))
+    }
+}
+
+impl From<Vec<Block>> for StatusLine {
+    fn from(blocks: Vec<Block>) -> StatusLine {
+        StatusLine { blocks }
+    }
+}
+
+impl From<StatusLine> for Vec<Block> {
+    fn from(line: StatusLine) -> Vec<Block> {
+        line.blocks
+    }
+}
+
+impl FromIterator<Block> for StatusLine {
+    fn from_iter<I: IntoIterator<Item = Block>>(iter: I) -> StatusLine {
+        StatusLine {
+            blocks: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl IntoIterator for StatusLine {
+    type Item = Block;
+    type IntoIter = vec::IntoIter<Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StatusLine {
+    type Item = &'a Block;
+    type IntoIter = slice::Iter<'a, Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut StatusLine {
+    type Item = &'a mut Block;
+    type IntoIter = slice::IterMut<'a, Block>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.blocks.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{BlockBuilder, ClickEventBuilder, MouseButton};
+
+    fn line() -> StatusLine {
+        vec![
+            BlockBuilder::new("wlan")
+                .name("network")
+                .instance("wlan0")
+                .build(),
+            BlockBuilder::new("eth")
+                .name("network")
+                .instance("eth0")
+                .build(),
+            BlockBuilder::new("time").name("clock").build(),
+            BlockBuilder::new("anonymous").build(),
+        ]
+        .into()
+    }
+
+    fn texts(line: &StatusLine) -> Vec<&str> {
+        line.iter().map(Block::full_text).collect()
+    }
+
+    #[test]
+    fn it_looks_up_blocks_by_name_and_instance() {
+        let line = line();
+
+        assert_eq!(line.position("network", Some("eth0")), Some(1));
+        assert_eq!(line.get("clock", None).unwrap().full_text(), "time");
+        assert!(line.get("clock", Some("utc")).is_none());
+        assert!(line.get("network", None).is_none());
+        assert!(!line.contains("missing", None));
+
+        let event = ClickEventBuilder::new("network", MouseButton::Left)
+            .instance("wlan0")
+            .build();
+        assert_eq!(line.clicked_block(&event).unwrap().full_text(), "wlan");
+    }
+
+    #[test]
+    fn it_modifies_blocks_by_key() {
+        let mut line = line();
+
+        let old = line
+            .replace(
+                "clock",
+                None,
+                BlockBuilder::new("12:00").name("clock").build(),
+            )
+            .unwrap();
+        assert_eq!(old.full_text(), "time");
+
+        assert!(line.insert_before("network", Some("eth0"), BlockBuilder::new("vpn").build()));
+        assert!(line.insert_after("clock", None, BlockBuilder::new("date").build()));
+        assert_eq!(
+            texts(&line),
+            vec!["wlan", "vpn", "eth", "12:00", "date", "anonymous"]
+        );
+
+        assert_eq!(
+            line.remove("network", Some("wlan0")).unwrap().full_text(),
+            "wlan"
+        );
+        assert!(line.remove("network", Some("wlan0")).is_none());
+
+        assert!(!line.insert_after("missing", None, BlockBuilder::new("lost").build()));
+        assert!(line
+            .replace("missing", None, BlockBuilder::new("lost").build())
+            .is_none());
+
+        line.get_mut("clock", None).unwrap().set_full_text("12:01");
+        assert_eq!(
+            texts(&line),
+            vec!["vpn", "eth", "12:01", "date", "anonymous"]
+        );
+    }
+
+    #[test]
+    fn it_diffs_against_the_next_line() {
+        let old = line();
+        let mut next = line();
+        next.get_mut("clock", None).unwrap().set_full_text("12:00");
+        next.remove("network", Some("eth0"));
+
+        let diff = old.diff(&next);
+        assert_eq!(diff.removed(), &[1]);
+        assert_eq!(diff.changed()[0].old_index, 2);
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn it_serdes_as_json_array() {
+        let json = r#"[{"full_text":"a","name":"first"},{"full_text":"b"}]"#;
+        let line: StatusLine = json.parse().expect("Could not parse");
+
+        assert_eq!(line.len(), 2);
+        assert_eq!(line.get("first", None).unwrap().full_text(), "a");
+
+        let serialized = serde_json::to_string(&line).unwrap();
+        assert!(serialized.starts_with("[{"));
+        let reparsed: Vec<Block> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reparsed.len(), 2);
+    }
+}
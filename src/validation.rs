@@ -0,0 +1,399 @@
+extern crate serde_json;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use serde_json::{Map, Value};
+use super::{parse_markup, Block, Field, Markup, MarkupError, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    EmptyFullText,
+    MissingFullText,
+    InvalidColor(String),
+    // A value that could not be read, with the reason.
+    InvalidValue(String),
+    InvalidMarkup(MarkupError),
+    ShortTextLongerThanFullText,
+    // Index of the earlier block with the same name and instance.
+    DuplicateKey(usize),
+    // Click events for the block cannot be told apart from other blocks.
+    InstanceWithoutName,
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Problem::MissingFullText
+            | Problem::InvalidColor(_)
+            | Problem::InvalidValue(_)
+            | Problem::InvalidMarkup(_)
+            | Problem::DuplicateKey(_) => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::EmptyFullText => write!(f, "full_text is empty"),
+            Problem::MissingFullText => write!(f, "full_text is missing"),
+            Problem::InvalidColor(ref value) => write!(f, "invalid color {:?}", value),
+            Problem::InvalidValue(ref reason) => write!(f, "invalid value: {}", reason),
+            Problem::InvalidMarkup(ref error) => write!(f, "invalid pango markup: {}", error),
+            Problem::ShortTextLongerThanFullText => {
+                write!(f, "short_text is longer than full_text")
+            }
+            Problem::DuplicateKey(first) => {
+                write!(f, "name and instance are already used by block {}", first)
+            }
+            Problem::InstanceWithoutName => write!(f, "instance is set without a name"),
+        }
+    }
+}
+
+// Blocks validated on their own are reported with index 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub index: usize,
+    pub field: Field,
+    pub problem: Problem,
+}
+
+impl Warning {
+    pub fn severity(&self) -> Severity {
+        self.problem.severity()
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "block {}: {}: {}",
+            self.index,
+            field_key(&self.field),
+            self.problem
+        )
+    }
+}
+
+fn field_key(field: &Field) -> &str {
+    match *field {
+        Field::Name => "name",
+        Field::Instance => "instance",
+        Field::FullText => "full_text",
+        Field::ShortText => "short_text",
+        Field::Color => "color",
+        Field::Background => "background",
+        Field::Border => "border",
+        Field::BorderTop => "border_top",
+        Field::BorderBottom => "border_bottom",
+        Field::BorderLeft => "border_left",
+        Field::BorderRight => "border_right",
+        Field::MinWidth => "min_width",
+        Field::Alignment => "align",
+        Field::Urgent => "urgent",
+        Field::Separator => "separator",
+        Field::SeparatorBlockWidth => "separator_block_width",
+        Field::Markup => "markup",
+        Field::Extra(ref key) => key,
+    }
+}
+
+pub fn validate_block(index: usize, block: &Block) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut warn = |field: Field, problem: Problem| {
+        warnings.push(Warning {
+            index,
+            field,
+            problem,
+        })
+    };
+
+    if block.full_text().is_empty() {
+        warn(Field::FullText, Problem::EmptyFullText);
+    }
+
+    if block.markup() == Markup::Pango {
+        if let Err(error) = parse_markup(block.full_text()) {
+            warn(Field::FullText, Problem::InvalidMarkup(error));
+        }
+        if let Some(Err(error)) = block.short_text().map(parse_markup) {
+            warn(Field::ShortText, Problem::InvalidMarkup(error));
+        }
+    }
+
+    // Markup is left out of the comparison where it can be parsed.
+    if let (Some(short_text), Some(plain_short_text)) =
+        (block.short_text(), block.plain_short_text())
+    {
+        let full_length = visible_length(block.plain_full_text(), block.full_text());
+        let short_length = visible_length(plain_short_text, short_text);
+        if short_length > full_length {
+            warn(Field::ShortText, Problem::ShortTextLongerThanFullText);
+        }
+    }
+
+    if block.name().is_none() && block.instance().is_some() {
+        warn(Field::Instance, Problem::InstanceWithoutName);
+    }
+
+    warnings
+}
+
+fn visible_length(plain: Result<Cow<str>, MarkupError>, raw: &str) -> usize {
+    plain
+        .as_ref()
+        .map(|text| text.as_ref())
+        .unwrap_or(raw)
+        .chars()
+        .count()
+}
+
+pub fn validate_blocks(blocks: &[Block]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut seen = HashMap::new();
+
+    for (index, block) in blocks.iter().enumerate() {
+        warnings.extend(validate_block(index, block));
+
+        if let Some(name) = block.name() {
+            let first = *seen.entry((name, block.instance())).or_insert(index);
+            if first != index {
+                warnings.push(Warning {
+                    index,
+                    field: Field::Name,
+                    problem: Problem::DuplicateKey(first),
+                });
+            }
+        }
+    }
+
+    warnings
+}
+
+// Fields are checked one by one before the blocks are parsed, so that a bad value is reported as
+// a warning for its block, and the block is validated as if the field was not set. This also
+// covers invalid colors, which a `Block` cannot hold. Only input that is not an array of objects
+// is an error.
+pub fn validate_json(json: &str) -> Result<Vec<Warning>, ParseError> {
+    let objects: Vec<Map<String, Value>> = serde_json::from_str(json)?;
+    let mut field_warnings = Vec::new();
+    let mut blocks = Vec::with_capacity(objects.len());
+
+    for (index, mut object) in objects.into_iter().enumerate() {
+        let mut warn = |field: Field, problem: Problem| {
+            field_warnings.push(Warning {
+                index,
+                field,
+                problem,
+            })
+        };
+
+        if !object.contains_key("full_text") {
+            warn(Field::FullText, Problem::MissingFullText);
+        }
+
+        let keys: Vec<String> = object.keys().cloned().collect();
+        for key in keys {
+            let error = match check_field(&key, &object[&key]) {
+                Ok(()) => continue,
+                Err(error) => error,
+            };
+
+            let field = Field::from_key(&key);
+            let problem = match (&field, object.remove(&key)) {
+                (&Field::Color, Some(Value::String(color)))
+                | (&Field::Background, Some(Value::String(color)))
+                | (&Field::Border, Some(Value::String(color))) => Problem::InvalidColor(color),
+                _ => Problem::InvalidValue(error.to_string()),
+            };
+            warn(field, problem);
+        }
+
+        // Blocks without a usable full_text are validated with an empty one, which is not
+        // reported again.
+        if !matches!(object.get("full_text"), Some(Value::String(_))) {
+            object.insert(String::from("full_text"), Value::from(""));
+        }
+
+        blocks.push(serde_json::from_value::<Block>(Value::Object(object))?);
+    }
+
+    let mut warnings: Vec<Warning> = validate_blocks(&blocks)
+        .into_iter()
+        .filter(|warning| {
+            warning.problem != Problem::EmptyFullText
+                || !field_warnings
+                    .iter()
+                    .any(|other| other.index == warning.index && other.field == Field::FullText)
+        })
+        .collect();
+    warnings.extend(field_warnings);
+    warnings.sort_by_key(|warning| warning.index);
+    Ok(warnings)
+}
+
+// Reads the field on its own, in an otherwise valid block.
+fn check_field(key: &str, value: &Value) -> Result<(), serde_json::Error> {
+    let mut object = Map::new();
+    object.insert(String::from("full_text"), Value::from(""));
+    object.insert(key.to_owned(), value.clone());
+    serde_json::from_value::<Block>(Value::Object(object)).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::BlockBuilder;
+
+    fn problems(warnings: &[Warning]) -> Vec<(usize, Field, Problem)> {
+        warnings
+            .iter()
+            .map(|warning| {
+                (
+                    warning.index,
+                    warning.field.clone(),
+                    warning.problem.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_accepts_good_blocks() {
+        let block = BlockBuilder::new("<b>50%</b> &amp; more")
+            .name("battery")
+            .instance("BAT0")
+            .short_text("50%")
+            .markup(Markup::Pango)
+            .build();
+
+        assert!(block.validate().is_empty());
+    }
+
+    #[test]
+    fn it_warns_about_block_problems() {
+        let block = BlockBuilder::new("")
+            .instance("wlan0")
+            .short_text("long")
+            .build();
+
+        assert_eq!(
+            problems(&block.validate()),
+            vec![
+                (0, Field::FullText, Problem::EmptyFullText),
+                (0, Field::ShortText, Problem::ShortTextLongerThanFullText),
+                (0, Field::Instance, Problem::InstanceWithoutName),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_compares_visible_text_lengths() {
+        let block = BlockBuilder::new("<b>50%</b>")
+            .short_text("<span foreground='#ff0000'>50</span>")
+            .markup(Markup::Pango)
+            .build();
+        assert!(block.validate().is_empty());
+
+        let block = BlockBuilder::new("<b>5%</b>")
+            .short_text("<i>50%</i>")
+            .markup(Markup::Pango)
+            .build();
+        assert_eq!(
+            problems(&block.validate()),
+            vec![(0, Field::ShortText, Problem::ShortTextLongerThanFullText)]
+        );
+    }
+
+    #[test]
+    fn it_checks_pango_markup() {
+        for text in &[
+            "<b>bold",
+            "<b>x</i>",
+            "<blink>x</blink>",
+            "a & b",
+            "&bogus;",
+        ] {
+            let block = BlockBuilder::new(*text).markup(Markup::Pango).build();
+            let warnings = block.validate();
+            assert_eq!(warnings.len(), 1, "{:?}", text);
+            assert!(matches!(warnings[0].problem, Problem::InvalidMarkup(_)));
+            assert_eq!(warnings[0].severity(), Severity::Error);
+
+            let block = BlockBuilder::new(*text).build();
+            assert!(block.validate().is_empty());
+        }
+    }
+
+    #[test]
+    fn it_finds_duplicate_keys() {
+        let blocks = vec![
+            BlockBuilder::new("a").name("cpu").build(),
+            BlockBuilder::new("b").name("cpu").instance("0").build(),
+            BlockBuilder::new("c").name("cpu").build(),
+        ];
+
+        assert_eq!(
+            problems(&validate_blocks(&blocks)),
+            vec![(2, Field::Name, Problem::DuplicateKey(0))]
+        );
+    }
+
+    #[test]
+    fn it_validates_raw_json() {
+        let json = r##"[{"full_text":"a","color":"red"},{"full_text":"","border":"#00ff00"}]"##;
+        let warnings = validate_json(json).expect("Could not parse");
+
+        assert_eq!(
+            problems(&warnings),
+            vec![
+                (0, Field::Color, Problem::InvalidColor(String::from("red"))),
+                (1, Field::FullText, Problem::EmptyFullText),
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "block 0: color: invalid color \"red\""
+        );
+
+        assert!(validate_json(r#"{"full_text":"a"}"#).is_err());
+        assert!(validate_json(r#"["a"]"#).is_err());
+    }
+
+    #[test]
+    fn it_reports_unreadable_fields_per_block() {
+        let json = r#"[{"color":"red"},{"full_text":"a","align":"middle","urgent":"yes"},{"full_text":3},{"full_text":""}]"#;
+        let warnings = validate_json(json).expect("Could not parse");
+
+        assert_eq!(
+            warnings
+                .iter()
+                .map(|warning| (warning.index, warning.field.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, Field::FullText),
+                (0, Field::Color),
+                (1, Field::Alignment),
+                (1, Field::Urgent),
+                (2, Field::FullText),
+                (3, Field::FullText),
+            ]
+        );
+        assert_eq!(warnings[0].problem, Problem::MissingFullText);
+        assert!(
+            matches!(warnings[2].problem, Problem::InvalidValue(ref reason) if reason.contains("middle"))
+        );
+        assert!(matches!(warnings[4].problem, Problem::InvalidValue(_)));
+        assert_eq!(warnings[5].problem, Problem::EmptyFullText);
+    }
+}
@@ -0,0 +1,198 @@
+extern crate serde_json;
+
+use std::io::{self, Write};
+use super::{Block, ClickEvent, Header};
+
+// Shared with the async sinks so that both produce byte-identical streams.
+pub fn write_status_preamble<W: Write>(writer: &mut W, header: &Header) -> io::Result<()> {
+    writeln!(writer, "{}", header)?;
+    writeln!(writer, "[")
+}
+
+pub fn write_status_line<W: Write>(
+    writer: &mut W,
+    blocks: &[Block],
+    first: bool,
+) -> io::Result<()> {
+    if !first {
+        writer.write_all(b",")?;
+    }
+    serde_json::to_writer(&mut *writer, blocks)?;
+    writeln!(writer)
+}
+
+pub fn write_click_preamble<W: Write>(writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "[")
+}
+
+pub fn write_click_event<W: Write>(
+    writer: &mut W,
+    event: &ClickEvent,
+    first: bool,
+) -> io::Result<()> {
+    if first {
+        writeln!(writer, "{}", event)
+    } else {
+        writeln!(writer, ",{}", event)
+    }
+}
+
+pub fn write_closing<W: Write>(writer: &mut W) -> io::Result<()> {
+    writeln!(writer, "]")
+}
+
+pub struct StatusLineWriter<W: Write> {
+    writer: W,
+    first_line: bool,
+}
+
+impl<W: Write> StatusLineWriter<W> {
+    pub fn new(mut writer: W, header: &Header) -> io::Result<StatusLineWriter<W>> {
+        write_status_preamble(&mut writer, header)?;
+        writer.flush()?;
+
+        Ok(StatusLineWriter {
+            writer,
+            first_line: true,
+        })
+    }
+
+    pub fn write_line(&mut self, blocks: &[Block]) -> io::Result<()> {
+        write_status_line(&mut self.writer, blocks, self.first_line)?;
+        self.writer.flush()?;
+
+        self.first_line = false;
+        Ok(())
+    }
+
+    pub fn close(mut self) -> io::Result<W> {
+        write_closing(&mut self.writer)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+}
+
+pub struct ClickEventWriter<W: Write> {
+    writer: W,
+    first_event: bool,
+}
+
+impl<W: Write> ClickEventWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<ClickEventWriter<W>> {
+        write_click_preamble(&mut writer)?;
+        writer.flush()?;
+
+        Ok(ClickEventWriter {
+            writer,
+            first_event: true,
+        })
+    }
+
+    pub fn write_event(&mut self, event: &ClickEvent) -> io::Result<()> {
+        write_click_event(&mut self.writer, event, self.first_event)?;
+        self.writer.flush()?;
+
+        self.first_event = false;
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{
+        BlockBuilder, ClickEventBuilder, ClickEventReader, HeaderBuilder, MouseButton,
+        StatusLineReader,
+    };
+
+    #[test]
+    fn it_writes_protocol_stream() {
+        let header = HeaderBuilder::new().click_events(true).build();
+        let mut writer = StatusLineWriter::new(Vec::new(), &header).unwrap();
+
+        writer
+            .write_line(&[BlockBuilder::new("a").build()])
+            .unwrap();
+        writer
+            .write_line(&[
+                BlockBuilder::new("b").build(),
+                BlockBuilder::new("c").build(),
+            ])
+            .unwrap();
+        let output = String::from_utf8(writer.close().unwrap()).unwrap();
+
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], header.to_string());
+        assert_eq!(lines[1], "[");
+        assert!(lines[2].starts_with("[{"));
+        assert!(lines[3].starts_with(",[{"));
+        assert_eq!(lines[4], "]");
+    }
+
+    #[test]
+    fn it_roundtrips_through_reader() {
+        let header = HeaderBuilder::new().build();
+        let mut writer = StatusLineWriter::new(Vec::new(), &header).unwrap();
+        writer
+            .write_line(&[BlockBuilder::new("a").build()])
+            .unwrap();
+        writer.write_line(&[]).unwrap();
+        writer
+            .write_line(&[BlockBuilder::new("b").build()])
+            .unwrap();
+        let output = writer.close().unwrap();
+
+        let reader = StatusLineReader::new(&output[..]).unwrap();
+        assert_eq!(reader.header(), &header);
+
+        let lines: Vec<Vec<Block>> = reader.collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].full_text(), "a");
+        assert!(lines[1].is_empty());
+        assert_eq!(lines[2][0].full_text(), "b");
+    }
+
+    #[test]
+    fn it_writes_click_events() {
+        let mut writer = ClickEventWriter::new(Vec::new()).unwrap();
+        writer
+            .write_event(&ClickEventBuilder::new("first", MouseButton::Left).build())
+            .unwrap();
+        writer
+            .write_event(
+                &ClickEventBuilder::new("second", MouseButton::WheelUp)
+                    .instance("b")
+                    .coordinates(10, 20)
+                    .build(),
+            )
+            .unwrap();
+        let output = writer.into_inner();
+
+        let text = String::from_utf8(output.clone()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "[");
+        assert!(lines[1].starts_with('{'));
+        assert!(lines[2].starts_with(",{"));
+
+        let events: Vec<ClickEvent> = ClickEventReader::new(&output[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name(), "first");
+        assert_eq!(events[1].instance(), Some("b"));
+        assert_eq!(events[1].coordinates(), Some((10, 20)));
+    }
+}